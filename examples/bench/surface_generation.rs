@@ -0,0 +1,187 @@
+use std::time::Instant;
+use cgmath::*;
+use wgpu_gpu_surfaces::colormap;
+use wgpu_gpu_surfaces::math_func as mf;
+use wgpu_gpu_surfaces::surface_data::{IParametricSurface, ISimpleSurface, ISurfaceOutput};
+
+// times the CPU surface generation. full generation, with a new output every frame, is timed against
+// a serial reference, which builds the surface the way the generators did before they were
+// parallelized: a range pass into a Vec<Vec>, then every vertex attribute pushed one by one. the
+// generators are timed on one thread and on all threads. the in-place paths, which write into the
+// output of the previous frame (new_into for the torus, update_time for the animated sinc), do less
+// work than a full generation and are reported in a table of their own. run with --release:
+// cargo run --release --example surface_generation
+fn time_ms(frames:u32, mut f:impl FnMut(u32)) -> f64 {
+    f(0);
+    let start = Instant::now();
+    for i in 0..frames {
+        f(i);
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / frames as f64
+}
+
+// the time on one thread and on all threads
+fn threads_ms(frames:u32, mut f:impl FnMut(u32) + Send) -> (f64, f64) {
+    let single = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let t1 = single.install(|| time_ms(frames, &mut f));
+    (t1, time_ms(frames, f))
+}
+
+fn run_full(label:&str, frames:u32, mut serial:impl FnMut(u32) -> ISurfaceOutput,
+mut generate:impl FnMut(u32) -> ISurfaceOutput + Send) {
+    let ts = time_ms(frames, |i| { serial(i); });
+    let (t1, tn) = threads_ms(frames, |i| { generate(i); });
+    println!("{:<28} {:>10.2} {:>10.2} {:>10.2} {:>8.1}x", label, ts, t1, tn, ts / tn);
+}
+
+fn run_in_place(label:&str, frames:u32, mut update:impl FnMut(u32, &mut ISurfaceOutput) + Send) {
+    let mut out = ISurfaceOutput::default();
+    let (t1, tn) = threads_ms(frames, |i| update(i, &mut out));
+    println!("{:<28} {:>10.2} {:>10.2} {:>8.1}x", label, t1, tn, t1 / tn);
+}
+
+fn tangent(du:Vector3<f32>, dv:Vector3<f32>, n:Vector3<f32>) -> [f32; 4] {
+    let t = (du - n * n.dot(du)).normalize();
+    [t.x, t.y, t.z, if n.cross(t).dot(dv) < 0.0 { -1.0 } else { 1.0 }]
+}
+
+fn grid(nu:u32, nv:u32, out:&mut ISurfaceOutput) {
+    for i in 0..nu {
+        for j in 0..nv {
+            let (idx0, idx1) = (j + i * (nv + 1), j + 1 + i * (nv + 1));
+            let (idx2, idx3) = (j + 1 + (i + 1) * (nv + 1), j + (i + 1) * (nv + 1));
+            out.indices.extend([idx0, idx1, idx2, idx2, idx3, idx0]);
+            out.indices2.extend([idx0, idx1, idx0, idx3]);
+        }
+    }
+}
+
+fn serial_torus(n:u16) -> ISurfaceOutput {
+    let f = mf::torus;
+    let d = 2.0 * std::f32::consts::PI / n as f32;
+    let eps = 0.01 * d;
+    let mut pts: Vec<Vec<[f32; 3]>> = vec![];
+    let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+    for i in 0..=n {
+        let mut row: Vec<[f32; 3]> = vec![];
+        for j in 0..=n {
+            let p = f(d * i as f32, d * j as f32);
+            for k in 0..3 {
+                (pmin[k], pmax[k]) = (pmin[k].min(p[k]), pmax[k].max(p[k]));
+            }
+            row.push(p);
+        }
+        pts.push(row);
+    }
+    let dist = (pmax[0] - pmin[0]).max(pmax[1] - pmin[1]).max(pmax[2] - pmin[2]);
+    let cdata = colormap::colormap_data("jet");
+    let cdata2 = colormap::colormap_data("white");
+    let mut out = ISurfaceOutput::default();
+    for i in 0..=n {
+        let u = d * i as f32;
+        for j in 0..=n {
+            let v = d * j as f32;
+            let p = pts[i as usize][j as usize];
+            let p = [0, 1, 2].map(|k| 2.0 * (p[k] - 0.5 * (pmin[k] + pmax[k])) / dist);
+            out.positions.push(p);
+            let nu = Vector3::from(f(u + eps, v)) - Vector3::from(f(u - eps, v));
+            let nv = Vector3::from(f(u, v + eps)) - Vector3::from(f(u, v - eps));
+            let normal = nu.cross(nv).normalize();
+            out.normals.push(normal.into());
+            out.tangents.push(tangent(nu, nv, normal));
+            out.colors.push(colormap::color_lerp(cdata, -1.0, 1.0, p[1]));
+            out.colors2.push(colormap::color_lerp(cdata2, -1.0, 1.0, p[1]));
+            out.uvs.push([i as f32 / n as f32, j as f32 / n as f32]);
+        }
+    }
+    grid(n as u32, n as u32, &mut out);
+    out.reject_non_finite();
+    out.remove_unused_vertices();
+    out
+}
+
+fn serial_sinc(n:u16, t:f32) -> ISurfaceOutput {
+    let f = mf::sinc;
+    let d = 16.0 / n as f32;
+    let eps = 0.01 * d;
+    let (mut ymin, mut ymax) = (f32::MAX, f32::MIN);
+    for i in 0..=n {
+        for j in 0..=n {
+            let y = f(-8.0 + d * i as f32, -8.0 + d * j as f32, t)[1];
+            (ymin, ymax) = (ymin.min(y), ymax.max(y));
+        }
+    }
+    let normalize = |p:[f32; 3]| Vector3::new(p[0] / 8.0, (-1.0 + 2.0 * (p[1] - ymin) / (ymax - ymin)) * 0.5, p[2] / 8.0);
+    let cdata = colormap::colormap_data("jet");
+    let cdata2 = colormap::colormap_data("white");
+    let mut out = ISurfaceOutput::default();
+    for i in 0..=n {
+        let x = -8.0 + d * i as f32;
+        for j in 0..=n {
+            let z = -8.0 + d * j as f32;
+            let p = normalize(f(x, z, t));
+            out.positions.push(p.into());
+            let nx = normalize(f(x + eps, z, t)) - normalize(f(x - eps, z, t));
+            let nz = normalize(f(x, z + eps, t)) - normalize(f(x, z - eps, t));
+            let normal = nx.cross(nz).normalize();
+            out.normals.push(normal.into());
+            out.tangents.push(tangent(nx, nz, normal));
+            out.colors.push(colormap::color_lerp(cdata, -0.5, 0.5, p.y));
+            out.colors2.push(colormap::color_lerp(cdata2, -0.5, 0.5, p.y));
+            out.uvs.push([i as f32 / n as f32, j as f32 / n as f32]);
+        }
+    }
+    grid(n as u32, n as u32, &mut out);
+    out.reject_non_finite();
+    out
+}
+
+fn torus(resolution:u16) -> IParametricSurface {
+    IParametricSurface {
+        surface_type: 21,
+        u_resolution: resolution,
+        v_resolution: resolution,
+        ..Default::default()
+    }
+}
+
+fn sinc(resolution:u16) -> ISimpleSurface {
+    ISimpleSurface {
+        surface_type: 0,
+        x_resolution: resolution,
+        z_resolution: resolution,
+        ..Default::default()
+    }
+}
+
+fn main() {
+    let resolutions = [200u16, 500, 1000];
+    let frames = |resolution:u16| if resolution > 500 { 5 } else { 20 };
+    println!("{} threads, milliseconds per frame", rayon::current_num_threads());
+
+    println!("\nfull generation, a new output every frame");
+    println!("{:<28} {:>10} {:>10} {:>10} {:>9}", "surface", "serial", "1 thread", "threads", "speedup");
+    for resolution in resolutions {
+        let mut torus = torus(resolution);
+        run_full(&format!("torus {0}x{0}", resolution), frames(resolution), |_| serial_torus(resolution),
+        |_| torus.new());
+
+        let mut sinc = sinc(resolution);
+        run_full(&format!("animated sinc {0}x{0}", resolution), frames(resolution),
+        |i| serial_sinc(resolution, 0.05 * i as f32), |i| {
+            sinc.t = 0.05 * i as f32;
+            sinc.new()
+        });
+    }
+
+    println!("\nin place, into the output of the previous frame");
+    println!("{:<28} {:>10} {:>10} {:>9}", "surface", "1 thread", "threads", "speedup");
+    for resolution in resolutions {
+        let mut torus = torus(resolution);
+        run_in_place(&format!("torus new_into {0}x{0}", resolution), frames(resolution), |_, out| torus.new_into(out));
+
+        let mut sinc = sinc(resolution);
+        run_in_place(&format!("sinc update_time {0}x{0}", resolution), frames(resolution),
+        |i, out| { sinc.update_time(0.05 * i as f32, out); });
+    }
+}
@@ -13,6 +13,8 @@ struct SimpleSurfaceParams {
     colormapDirection: f32,
     colormapReverse: f32,
     animationTime: f32,
+    normalization: f32, // 0: y-range of getDataRange, 1: yRange
+    yRange: vec2f,
 }
 
 @group(0) @binding(0) var<storage, read_write> vda : VertexDataArray;
@@ -37,6 +39,10 @@ fn getUv(i:u32, j:u32) -> vec2f {
 	xmax = dr.xRange[1];
 	ymin = dr.yRange[0];
 	ymax = dr.yRange[1];
+	if (u32(ssp.normalization) == 1u) {
+		ymin = ssp.yRange[0];
+		ymax = ssp.yRange[1];
+	}
 	zmin = dr.zRange[0];
 	zmax = dr.zRange[1];
     aspect = dr.aspectRatio;
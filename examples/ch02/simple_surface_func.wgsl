@@ -35,6 +35,31 @@ fn poles(x:f32, z:f32, t:f32) -> vec3f{
 	return vec3(x, y, z);
 }
 
+fn monkey_saddle(x:f32, z:f32, t:f32) -> vec3f{
+	return vec3(x, x*x*x - 3.0*x*z*z, z);
+}
+
+fn rosenbrock(x:f32, z:f32, t:f32) -> vec3f{
+	return vec3(x, (1.0 - x)*(1.0 - x) + 100.0*(z - x*x)*(z - x*x), z);
+}
+
+fn himmelblau(x:f32, z:f32, t:f32) -> vec3f{
+	let a = x*x + z - 11.0;
+	let b = x + z*z - 7.0;
+	return vec3(x, a*a + b*b, z);
+}
+
+fn ackley(x:f32, z:f32, t:f32) -> vec3f{
+	let pi = 3.14159265359;
+	let y = -20.0*exp(-0.2*sqrt(0.5*(x*x + z*z))) - exp(0.5*(cos(2.0*pi*x) + cos(2.0*pi*z))) + exp(1.0) + 20.0;
+	return vec3(x, y, z);
+}
+
+fn ripple(x:f32, z:f32, t:f32) -> vec3f{
+	let r = sqrt(x*x + z*z);
+	return vec3(x, sin(2.0*r - 2.0*t)*exp(-0.1*r), z);
+}
+
 fn getDataRange(funcSelection:u32) -> DataRange{
 	var dr:DataRange;
 
@@ -48,6 +73,31 @@ fn getDataRange(funcSelection:u32) -> DataRange{
 		dr.yRange = vec2(-6.5, 8.1);
 		dr.zRange = vec2(-3.0, 3.0);
 		dr.aspectRatio = 0.9;
+	} else if (funcSelection == 3u) { // monkey saddle
+		dr.xRange = vec2(-1.0, 1.0);
+		dr.yRange = vec2(-2.0, 2.0);
+		dr.zRange = vec2(-1.0, 1.0);
+		dr.aspectRatio = 0.8;
+	} else if (funcSelection == 4u) { // rosenbrock
+		dr.xRange = vec2(-2.0, 2.0);
+		dr.yRange = vec2(0.0, 2509.0);
+		dr.zRange = vec2(-1.0, 3.0);
+		dr.aspectRatio = 0.6;
+	} else if (funcSelection == 5u) { // himmelblau
+		dr.xRange = vec2(-5.0, 5.0);
+		dr.yRange = vec2(0.0, 890.0);
+		dr.zRange = vec2(-5.0, 5.0);
+		dr.aspectRatio = 0.6;
+	} else if (funcSelection == 6u) { // ackley
+		dr.xRange = vec2(-5.0, 5.0);
+		dr.yRange = vec2(0.0, 14.303);
+		dr.zRange = vec2(-5.0, 5.0);
+		dr.aspectRatio = 0.5;
+	} else if (funcSelection == 7u) { // ripple
+		dr.xRange = vec2(-10.0, 10.0);
+		dr.yRange = vec2(-1.0, 1.0);
+		dr.zRange = vec2(-10.0, 10.0);
+		dr.aspectRatio = 0.3;
 	} else { // poles
 		dr.xRange = vec2(-8.0, 8.0);
 		dr.yRange = vec2(-0.4, 3.3);
@@ -70,6 +120,21 @@ fn simpleSurfaceFunc(x:f32, z:f32, t:f32, funcSelection:u32) -> vec3f{
 	else if (funcSelection == 2u) { // poles
 		pos = poles(x, z, t);
 	}
+	else if (funcSelection == 3u) { // monkey saddle
+		pos = monkey_saddle(x, z, t);
+	}
+	else if (funcSelection == 4u) { // rosenbrock
+		pos = rosenbrock(x, z, t);
+	}
+	else if (funcSelection == 5u) { // himmelblau
+		pos = himmelblau(x, z, t);
+	}
+	else if (funcSelection == 6u) { // ackley
+		pos = ackley(x, z, t);
+	}
+	else if (funcSelection == 7u) { // ripple
+		pos = ripple(x, z, t);
+	}
 	
 	return pos;
 }
\ No newline at end of file
@@ -2,14 +2,14 @@ use bytemuck::cast_slice;
 use cgmath::{Matrix, Matrix4, SquareMatrix};
 use rand::{rngs::ThreadRng, Rng};
 use std::iter;
-use wgpu::{util::DeviceExt, VertexBufferLayout};
+use wgpu::util::DeviceExt;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
 use wgpu_simplified as ws;
-use wgpu_gpu_surfaces::{colormap, surface_data::get_surface_type};
+use wgpu_gpu_surfaces::{colormap, vertex_data::{compute_grid_indices, index_buffer_data, IVertexData}, surface_data::get_surface_type};
 
 fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     let cdata = colormap::colormap_data(colormap_name);
@@ -28,7 +28,8 @@ struct State {
 
     cs_pipelines: Vec<wgpu::ComputePipeline>,
     cs_vertex_buffers: Vec<wgpu::Buffer>,
-    cs_index_buffers: Vec<wgpu::Buffer>,
+    index_buffers: Vec<wgpu::Buffer>,
+    index_formats: Vec<wgpu::IndexFormat>,
     cs_uniform_buffers: Vec<wgpu::Buffer>,
     cs_bind_groups: Vec<wgpu::BindGroup>,
 
@@ -46,6 +47,7 @@ struct State {
     surface_type: u32,
     colormap_direction: u32,
     colormap_reverse: u32,
+    projection_4d: u32,
 
     rng: ThreadRng,
     t0: std::time::Instant,
@@ -82,10 +84,6 @@ impl State {
                 source: wgpu::ShaderSource::Wgsl(cs_comp_file.into()),
             });
 
-        let cs_indices = init
-            .device
-            .create_shader_module(wgpu::include_wgsl!("../ch02/indices_comp.wgsl"));
-
         // uniform data
         let camera_position = (1.5, 1.5, 1.5).into();
         let look_direction = (0.0, 0.0, 0.0).into();
@@ -177,11 +175,7 @@ impl State {
             ],
         );
 
-        let vertex_buffer_layout = VertexBufferLayout {
-            array_stride: 48,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4], // pos, norm, col
-        };
+        let vertex_buffer_layout = IVertexData::layout(); // pos, norm, col
 
         let pipeline_layout = init
             .device
@@ -200,11 +194,7 @@ impl State {
         };
         let pipeline = ppl.new(&init);
 
-        let vertex_buffer_layout2 = VertexBufferLayout {
-            array_stride: 48,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4], // pos, norm, col2
-        };
+        let vertex_buffer_layout2 = IVertexData::layout(); // pos, norm, col2
 
         let pipeline_layout2 =
             init.device
@@ -229,74 +219,28 @@ impl State {
 
         let resol = ws::round_to_multiple(resolution, 8);
         let vertices_count = resol * resol;
-        let triangles_count = 6 * (resol - 1) * (resol - 1);
-        let lines_count = 8 * (resol - 1) * (resol - 1);
 
         println!("resolution = {}", resol);
 
-        // create compute pipeline for indices
-        let cs_index_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+        // index buffers, made once since the resolution is fixed, with 16-bit indices when the
+        // vertex count allows it
+        let (indices, indices2) = compute_grid_indices(resol);
+        let triangles_count = indices.len() as u32;
+        let lines_count = indices2.len() as u32;
+        let (index_data, index_format) = index_buffer_data(&indices);
+        let (index_data2, index_format2) = index_buffer_data(&indices2);
+
+        let index_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            size: 4 * triangles_count as u64,
-            usage: wgpu::BufferUsages::INDEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+            contents: &index_data,
+            usage: wgpu::BufferUsages::INDEX,
         });
 
-        let cs_index_buffer2 = init.device.create_buffer(&wgpu::BufferDescriptor {
+        let index_buffer2 = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index 2 Buffer"),
-            size: 4 * lines_count as u64,
-            usage: wgpu::BufferUsages::INDEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let cs_index_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Index Uniform Buffer"),
-            size: 4,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+            contents: &index_data2,
+            usage: wgpu::BufferUsages::INDEX,
         });
-        init.queue
-            .write_buffer(&cs_index_uniform_buffer, 0, cast_slice(&[resol]));
-
-        let (cs_index_bind_group_layout, cs_index_bind_group) = ws::create_bind_group_storage(
-            &init.device,
-            vec![
-                wgpu::ShaderStages::COMPUTE,
-                wgpu::ShaderStages::COMPUTE,
-                wgpu::ShaderStages::COMPUTE,
-            ],
-            vec![
-                wgpu::BufferBindingType::Storage { read_only: false },
-                wgpu::BufferBindingType::Storage { read_only: false },
-                wgpu::BufferBindingType::Uniform,
-            ],
-            &[
-                cs_index_buffer.as_entire_binding(),
-                cs_index_buffer2.as_entire_binding(),
-                cs_index_uniform_buffer.as_entire_binding(),
-            ],
-        );
-
-        let cs_index_pipeline_layout =
-            init.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Compute Index Pipeline Layout"),
-                    bind_group_layouts: &[&cs_index_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
-        let cs_index_pipeline =
-            init.device
-                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                    label: Some("Compute Index Pipeline"),
-                    layout: Some(&cs_index_pipeline_layout),
-                    module: &cs_indices,
-                    entry_point: "cs_main",
-                });
 
         // create compute pipeline for surface
         let cs_vertex_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
@@ -336,11 +280,17 @@ impl State {
                 });
 
         let params = [resol, 22, 1, 0];
+        // 4D rotation angles (xy, xz, xw, yz, yw, zw), projection type and perspective distance
+        let params_4d = [0.0f32, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 3.0];
         let cs_vertex_uniform_buffer =
             init.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Vertex Uniform Buffer"),
-                    contents: bytemuck::cast_slice(&params),
+                    contents: &[
+                        bytemuck::cast_slice::<_, u8>(&params),
+                        bytemuck::cast_slice(&params_4d),
+                    ]
+                    .concat(),
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
 
@@ -401,11 +351,12 @@ impl State {
                 material_uniform_buffer,
             ],
 
-            cs_pipelines: vec![cs_index_pipeline, cs_pipeline],
+            cs_pipelines: vec![cs_pipeline],
             cs_vertex_buffers: vec![cs_vertex_buffer, cs_vertex_buffer2],
-            cs_index_buffers: vec![cs_index_buffer, cs_index_buffer2],
-            cs_uniform_buffers: vec![cs_index_uniform_buffer, cs_vertex_uniform_buffer],
-            cs_bind_groups: vec![cs_index_bind_group, cs_vertex_bind_group],
+            index_buffers: vec![index_buffer, index_buffer2],
+            index_formats: vec![index_format, index_format2],
+            cs_uniform_buffers: vec![cs_vertex_uniform_buffer],
+            cs_bind_groups: vec![cs_vertex_bind_group],
 
             view_mat,
             project_mat,
@@ -422,6 +373,7 @@ impl State {
             surface_type: 22,
             colormap_direction: 1,
             colormap_reverse: 0,
+            projection_4d: 1,
 
             rng: rand::thread_rng(),
             t0: std::time::Instant::now(),
@@ -466,7 +418,7 @@ impl State {
                     true
                 }
                 VirtualKeyCode::LControl => {
-                    self.surface_type = (self.surface_type + 1) % 23;
+                    self.surface_type = (self.surface_type + 1) % 33;
                     self.data_changed = true;
                     true
                 }
@@ -484,6 +436,10 @@ impl State {
                     self.data_changed = true;
                     true
                 }
+                VirtualKeyCode::P => {
+                    self.projection_4d = (self.projection_4d + 1) % 2;
+                    true
+                }
                 VirtualKeyCode::Q => {
                     self.rotation_speed += 0.1;
                     true
@@ -534,10 +490,20 @@ impl State {
             bytemuck::cast_slice(normal_ref),
         );
 
+        // animate the 4D rotation in the xw and yw planes
+        let t = dt.as_secs_f32();
+        let (xw, yw) = (0.5 * t, 0.3 * t);
+        let params_4d = [0.0, 0.0, xw, 0.0, yw, 0.0, self.projection_4d as f32, 3.0];
+        self.init.queue.write_buffer(
+            &self.cs_uniform_buffers[0],
+            16,
+            bytemuck::cast_slice(&params_4d),
+        );
+
         // change surface type for every 5 seconds
         let elapsed = self.t0.elapsed();
         if elapsed >= std::time::Duration::from_secs(5) && self.random_shape_change == 1 {
-            self.surface_type = self.rng.gen_range(0..=22) as u32;
+            self.surface_type = self.rng.gen_range(0..=32) as u32;
             let params = [
                 self.resolution,
                 self.surface_type,
@@ -545,7 +511,7 @@ impl State {
                 self.colormap_reverse,
             ];
             self.init.queue.write_buffer(
-                &self.cs_uniform_buffers[1],
+                &self.cs_uniform_buffers[0],
                 0,
                 bytemuck::cast_slice(&params),
             );
@@ -566,7 +532,7 @@ impl State {
                 self.colormap_reverse,
             ];
             self.init.queue.write_buffer(
-                &self.cs_uniform_buffers[1],
+                &self.cs_uniform_buffers[0],
                 0,
                 bytemuck::cast_slice(&params),
             );
@@ -592,23 +558,13 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
-        // compute pass for indices
-        {
-            let mut cs_index_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Index Pass"),
-            });
-            cs_index_pass.set_pipeline(&self.cs_pipelines[0]);
-            cs_index_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
-            cs_index_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
-        }
-
         // compute pass for vertices
         {
             let mut cs_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: Some("Compute Pass"),
             });
-            cs_pass.set_pipeline(&self.cs_pipelines[1]);
-            cs_pass.set_bind_group(0, &self.cs_bind_groups[1], &[]);
+            cs_pass.set_pipeline(&self.cs_pipelines[0]);
+            cs_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
             cs_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
         }
 
@@ -641,8 +597,8 @@ impl State {
                 render_pass.set_pipeline(&self.pipelines[0]);
                 render_pass.set_vertex_buffer(0, self.cs_vertex_buffers[0].slice(..));
                 render_pass.set_index_buffer(
-                    self.cs_index_buffers[0].slice(..),
-                    wgpu::IndexFormat::Uint32,
+                    self.index_buffers[0].slice(..),
+                    self.index_formats[0],
                 );
                 render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
                 render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
@@ -653,8 +609,8 @@ impl State {
                 render_pass.set_pipeline(&self.pipelines[1]);
                 render_pass.set_vertex_buffer(0, self.cs_vertex_buffers[1].slice(..));
                 render_pass.set_index_buffer(
-                    self.cs_index_buffers[1].slice(..),
-                    wgpu::IndexFormat::Uint32,
+                    self.index_buffers[1].slice(..),
+                    self.index_formats[1],
                 );
                 render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
                 render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
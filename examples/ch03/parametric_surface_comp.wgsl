@@ -13,6 +13,10 @@ struct ParametricSurfaceParams{
     funcSelection: u32,
     colormapDirection: u32,
     colormapReverse: u32,
+    angles4d: vec4f, // rotation angles of the 4D surfaces in the xy, xz, xw and yz planes
+    angles4d2: vec2f, // rotation angles in the yw and zw planes
+    projection4d: f32, // 0: stereographic, 1: perspective
+    distance4d: f32, // distance of the perspective viewpoint on the w-axis
 }
 
 @group(0) @binding(0) var<storage, read_write> vda : VertexDataArray;
@@ -48,6 +52,20 @@ fn getUv(id: vec3u) -> vec2f {
 	zmin = dr.zRange[0];
 	zmax = dr.zRange[1];	
 
+    // the projected 3-sphere fits into [-r, r]^3 for the perspective projection
+    if (psp.funcSelection >= 30u) {
+        var r = 3.0;
+        if (u32(psp.projection4d) == 1u) {
+            r = psp.distance4d / (psp.distance4d - 1.0);
+        }
+        xmin = -r;
+        xmax = r;
+        ymin = -r;
+        ymax = r;
+        zmin = -r;
+        zmax = r;
+    }
+
     du = (umax - umin)/(f32(psp.resolution) - 1.0);
     dv = (vmax - vmin)/(f32(psp.resolution) - 1.0);
     var u = umin + f32(id.x) * du;
@@ -57,6 +75,10 @@ fn getUv(id: vec3u) -> vec2f {
 
 fn normalizePoint(u:f32, v:f32) -> vec3f {
     var pos = parametricSurfaceFunc(u, v, psp.funcSelection);
+    if (psp.funcSelection >= 30u) {
+        let p4 = rotate4d(surface4dFunc(u, v, psp.funcSelection), psp.angles4d, psp.angles4d2);
+        pos = project4d(p4, u32(psp.projection4d), psp.distance4d);
+    }
     var distance = max(max(xmax - xmin, ymax - ymin), zmax - zmin);
 
     if(psp.colormapDirection == 0u){
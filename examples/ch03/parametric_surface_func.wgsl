@@ -260,6 +260,150 @@ fn wellenkugel(u:f32, v:f32) -> vec3f {
 	return vec3(x, y, z);
 }
 
+fn mobius_strip(u:f32, v:f32) -> vec3f {
+	var x = (1.0 + v * cos(0.5 * u)) * cos(u);
+	var y = v * sin(0.5 * u);
+	var z = (1.0 + v * cos(0.5 * u)) * sin(u);
+	return vec3(x, y, z);
+}
+
+fn dini(u:f32, v:f32) -> vec3f {
+	let a = 1.0;
+	let b = 0.2;
+	var x = a * cos(u) * sin(v);
+	var y = a * (cos(v) + log(tan(0.5 * v))) + b * u;
+	var z = a * sin(u) * sin(v);
+	return vec3(x, y, z);
+}
+
+fn catenoid(u:f32, v:f32) -> vec3f {
+	var x = cosh(v) * cos(u);
+	var y = v;
+	var z = cosh(v) * sin(u);
+	return vec3(x, y, z);
+}
+
+fn helicoid(u:f32, v:f32) -> vec3f {
+	var x = v * cos(u);
+	var y = u;
+	var z = v * sin(u);
+	return vec3(x, y, z);
+}
+
+// complex numbers stored as vec2(re, im)
+fn cmul(a:vec2f, b:vec2f) -> vec2f {
+	return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn cdiv(a:vec2f, b:vec2f) -> vec2f {
+	return vec2(a.x * b.x + a.y * b.y, a.y * b.x - a.x * b.y) / dot(b, b);
+}
+
+fn csin(a:vec2f) -> vec2f {
+	return vec2(sin(a.x) * cosh(a.y), cos(a.x) * sinh(a.y));
+}
+
+fn ccos(a:vec2f) -> vec2f {
+	return vec2(cos(a.x) * cosh(a.y), -sin(a.x) * sinh(a.y));
+}
+
+// theta_1(w) of the square lattice and its first two derivatives
+fn theta1(w:vec2f) -> array<vec2f, 3> {
+	var t = array<vec2f, 3>(vec2(0.0), vec2(0.0), vec2(0.0));
+	for (var n = 0; n < 6; n++) {
+		let k = f32(2 * n + 1);
+		let c = 2.0 * select(1.0, -1.0, n % 2 == 1) * exp(-pi * p2(f32(n) + 0.5));
+		t[0] += c * csin(k * w);
+		t[1] += c * k * ccos(k * w);
+		t[2] -= c * k * k * csin(k * w);
+	}
+	return t;
+}
+
+// Weierstrass zeta and p functions for the lattice with periods 1 and i
+fn weierstrass_zeta(z:vec2f) -> vec2f {
+	let t = theta1(pi * z);
+	return pi * z + pi * cdiv(t[1], t[0]);
+}
+
+fn weierstrass_p(z:vec2f) -> vec2f {
+	let t = theta1(pi * z);
+	return vec2(-pi, 0.0) - pi * pi * cdiv(cmul(t[2], t[0]) - cmul(t[1], t[1]), cmul(t[0], t[0]));
+}
+
+fn costa(u:f32, v:f32) -> vec3f {
+	let e1 = 6.875185818;
+	let z = vec2(u, v);
+	let i = vec2(0.0, 1.0);
+	let zeta = weierstrass_zeta(z);
+	let zeta1 = weierstrass_zeta(z - vec2(0.5, 0.0));
+	let zeta2 = weierstrass_zeta(z - vec2(0.0, 0.5));
+	let c = pi * pi / (4.0 * e1);
+	var x = 0.5 * (-zeta.x + pi * u + c + pi / (2.0 * e1) * (zeta1.x - zeta2.x));
+	var y = 0.5 * (-cmul(i, zeta).x + pi * v + c - pi / (2.0 * e1) * (cmul(i, zeta1).x - cmul(i, zeta2).x));
+	let p = weierstrass_p(z);
+	var h = 0.25 * sqrt(2.0 * pi) * log(length(cdiv(p - vec2(e1, 0.0), p + vec2(e1, 0.0))));
+	return vec3(x, h, y);
+}
+
+fn cross_cap(u:f32, v:f32) -> vec3f {
+	var x = cos(u) * sin(2.0 * v);
+	var y = cos(v) * cos(v) - cos(u) * cos(u) * sin(v) * sin(v);
+	var z = sin(u) * sin(2.0 * v);
+	return vec3(x, y, z);
+}
+
+fn roman(u:f32, v:f32) -> vec3f {
+	let sx = sin(v) * cos(u);
+	let sy = sin(v) * sin(u);
+	let sz = cos(v);
+	return vec3(sy * sz, sx * sy, sx * sz);
+}
+
+// 4D surfaces on the unit 3-sphere
+fn clifford_torus(u:f32, v:f32) -> vec4f {
+	let a = sqrt(0.5);
+	return vec4(a * cos(u), a * sin(u), a * cos(v), a * sin(v));
+}
+
+fn flat_torus(u:f32, v:f32) -> vec4f {
+	let eta = pi / 6.0;
+	return vec4(cos(eta) * cos(u), cos(eta) * sin(u), sin(eta) * cos(v), sin(eta) * sin(v));
+}
+
+fn hopf_torus(u:f32, v:f32) -> vec4f {
+	let theta = 0.5 * pi + 0.5 * sin(3.0 * u);
+	let c = cos(0.5 * theta);
+	let s = sin(0.5 * theta);
+	return vec4(c * cos(v), c * sin(v), s * cos(v - u), s * sin(v - u));
+}
+
+fn rotatePlane(p:vec4f, i:u32, j:u32, angle:f32) -> vec4f {
+	var q = p;
+	q[i] = cos(angle) * p[i] - sin(angle) * p[j];
+	q[j] = sin(angle) * p[i] + cos(angle) * p[j];
+	return q;
+}
+
+// rotation by the angles in the xy, xz, xw, yz (a) and yw, zw (b) planes, in that order
+fn rotate4d(p:vec4f, a:vec4f, b:vec2f) -> vec4f {
+	var q = rotatePlane(p, 0u, 1u, a.x);
+	q = rotatePlane(q, 0u, 2u, a.y);
+	q = rotatePlane(q, 0u, 3u, a.z);
+	q = rotatePlane(q, 1u, 2u, a.w);
+	q = rotatePlane(q, 1u, 3u, b.x);
+	return rotatePlane(q, 2u, 3u, b.y);
+}
+
+// projection 0: stereographic from (0, 0, 0, 1), 1: perspective from (0, 0, 0, distance)
+fn project4d(p:vec4f, projection:u32, distance:f32) -> vec3f {
+	var k = 1.0 / max(1.0 - p.w, 1e-3);
+	if (projection == 1u) {
+		k = distance / max(distance - p.w, 1e-3);
+	}
+	return k * p.xyz;
+}
+
 fn getDataRange(funcSelection:u32) -> DataRange {
 	var dr:DataRange;
 	if (funcSelection == 0u) { // klein-bottle
@@ -270,12 +414,12 @@ fn getDataRange(funcSelection:u32) -> DataRange {
 		dr.zRange = vec2<f32>(-1.517, 1.824);
 	} else if (funcSelection == 1u) { // astroid
 		dr.uRange = vec2(0.0, 2.0 * pi);
-		dr.vRange = vec2(0.0, 2.0 * pi);
+		dr.vRange = vec2(-0.5 * pi, 0.5 * pi);
 		dr.xRange = vec2(-1.0, 1.0);
 		dr.yRange = vec2(-1.0, 1.0);
 		dr.zRange = vec2(-1.0, 1.0);
 	} else if (funcSelection == 2u) { // astroid 2
-		dr.uRange = vec2(0.0, 2.0 * pi);
+		dr.uRange = vec2(0.0, pi);
 		dr.vRange = vec2(0.0, 2.0 * pi);
 		dr.xRange = vec2(-1.0, 1.0);
 		dr.yRange = vec2(-1.0, 1.0);
@@ -311,7 +455,7 @@ fn getDataRange(funcSelection:u32) -> DataRange {
 		dr.yRange = vec2<f32>(-10.8898, 10.8898);
 		dr.zRange = vec2<f32>(-27.258, 27.258);
 	} else if (funcSelection == 8u) { // figure 8
-		dr.uRange = vec2<f32>(0.0, 4.0*pi);
+		dr.uRange = vec2<f32>(0.0, 2.0*pi);
 		dr.vRange = vec2<f32>(0.0, 2.0*pi);
 		dr.xRange = vec2<f32>(-3.517, 3.5);
 		dr.yRange = vec2<f32>(-1.25, 1.25);
@@ -383,8 +527,8 @@ fn getDataRange(funcSelection:u32) -> DataRange {
 		dr.yRange = vec2<f32>(-0.917, 0.917);
 		dr.zRange = vec2<f32>(-5.6, 4.458);
 	} else if (funcSelection == 20u) { // steiner
-		dr.uRange = vec2<f32>(0.0, 1.999999*pi);
-		dr.vRange = vec2<f32>(0.0, 1.999999*pi);
+		dr.uRange = vec2<f32>(0.0, 2.0*pi);
+		dr.vRange = vec2<f32>(0.0, 0.5*pi);
 		dr.xRange = vec2<f32>(-0.5, 0.5);
 		dr.yRange = vec2<f32>(-0.5, 0.5);
 		dr.zRange = vec2<f32>(-0.5, 0.5);
@@ -400,11 +544,71 @@ fn getDataRange(funcSelection:u32) -> DataRange {
 		dr.xRange = vec2<f32>(-14.171, 14.173);
 		dr.yRange = vec2<f32>(-8.0, 10.626);
 		dr.zRange = vec2<f32>(-14.172, 14.172);
+	} else if (funcSelection == 23u) { // mobius strip
+		dr.uRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.vRange = vec2<f32>(-0.4, 0.4);
+		dr.xRange = vec2<f32>(-1.019, 1.4);
+		dr.yRange = vec2<f32>(-0.4, 0.4);
+		dr.zRange = vec2<f32>(-1.29, 1.29);
+	} else if (funcSelection == 24u) { // dini
+		dr.uRange = vec2<f32>(0.0, 4.0 * pi);
+		dr.vRange = vec2<f32>(0.05, 2.0);
+		dr.xRange = vec2<f32>(-1.0, 1.0);
+		dr.yRange = vec2<f32>(-2.69, 2.54);
+		dr.zRange = vec2<f32>(-1.0, 1.0);
+	} else if (funcSelection == 25u) { // catenoid
+		dr.uRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.vRange = vec2<f32>(-1.5, 1.5);
+		dr.xRange = vec2<f32>(-2.352, 2.352);
+		dr.yRange = vec2<f32>(-1.5, 1.5);
+		dr.zRange = vec2<f32>(-2.352, 2.352);
+	} else if (funcSelection == 26u) { // helicoid
+		dr.uRange = vec2<f32>(-pi, pi);
+		dr.vRange = vec2<f32>(-2.0, 2.0);
+		dr.xRange = vec2<f32>(-2.0, 2.0);
+		dr.yRange = vec2<f32>(-pi, pi);
+		dr.zRange = vec2<f32>(-2.0, 2.0);
+	} else if (funcSelection == 27u) { // costa
+		dr.uRange = vec2<f32>(0.05, 0.95);
+		dr.vRange = vec2<f32>(0.05, 0.95);
+		dr.xRange = vec2<f32>(-5.0, 5.0);
+		dr.yRange = vec2<f32>(-2.547, 2.547);
+		dr.zRange = vec2<f32>(-5.0, 5.0);
+	} else if (funcSelection == 28u) { // cross cap
+		dr.uRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.vRange = vec2<f32>(0.001, 0.5 * pi);
+		dr.xRange = vec2<f32>(-1.0, 1.0);
+		dr.yRange = vec2<f32>(-1.0, 1.0);
+		dr.zRange = vec2<f32>(-1.0, 1.0);
+	} else if (funcSelection == 29u) { // roman
+		dr.uRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.vRange = vec2<f32>(0.001, 0.999 * pi);
+		dr.xRange = vec2<f32>(-0.5, 0.5);
+		dr.yRange = vec2<f32>(-0.5, 0.5);
+		dr.zRange = vec2<f32>(-0.5, 0.5);
+	} else if (funcSelection >= 30u) { // 4D surfaces, perspective projection from distance 3
+		dr.uRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.vRange = vec2<f32>(0.0, 2.0 * pi);
+		dr.xRange = vec2<f32>(-1.5, 1.5);
+		dr.yRange = vec2<f32>(-1.5, 1.5);
+		dr.zRange = vec2<f32>(-1.5, 1.5);
 	}
 	return dr;
 }
 
 
+fn surface4dFunc(u:f32, v:f32, funcSelection:u32) -> vec4f {
+	var pos = vec4(0.0, 0.0, 0.0, 0.0);
+	if (funcSelection == 30u) { // clifford torus
+		pos = clifford_torus(u, v);
+	} else if (funcSelection == 31u) { // flat torus
+		pos = flat_torus(u, v);
+	} else if (funcSelection == 32u) { // hopf torus
+		pos = hopf_torus(u, v);
+	}
+	return pos;
+}
+
 fn parametricSurfaceFunc(u:f32, v:f32, funcSelection:u32) -> vec3f {
 	var pos = vec3(0.0, 0.0, 0.0);
 
@@ -455,6 +659,20 @@ fn parametricSurfaceFunc(u:f32, v:f32, funcSelection:u32) -> vec3f {
 		pos = torus(u, v);
 	} else if (funcSelection == 22u) { // wellenkugel
 		pos = wellenkugel(u, v);
+	} else if (funcSelection == 23u) { // mobius strip
+		pos = mobius_strip(u, v);
+	} else if (funcSelection == 24u) { // dini
+		pos = dini(u, v);
+	} else if (funcSelection == 25u) { // catenoid
+		pos = catenoid(u, v);
+	} else if (funcSelection == 26u) { // helicoid
+		pos = helicoid(u, v);
+	} else if (funcSelection == 27u) { // costa
+		pos = costa(u, v);
+	} else if (funcSelection == 28u) { // cross cap
+		pos = cross_cap(u, v);
+	} else if (funcSelection == 29u) { // roman
+		pos = roman(u, v);
 	}
 	return pos;
 }
\ No newline at end of file
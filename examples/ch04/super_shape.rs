@@ -1,14 +1,14 @@
 use bytemuck::cast_slice;
 use cgmath::{Matrix, Matrix4, SquareMatrix};
 use std::iter;
-use wgpu::{util::DeviceExt, VertexBufferLayout};
+use wgpu::util::DeviceExt;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
 use wgpu_simplified as ws;
-use wgpu_gpu_surfaces::colormap;
+use wgpu_gpu_surfaces::{colormap, vertex_data::{compute_grid_indices, index_buffer_data, IVertexData}, surface_data::get_super_shape_preset};
 
 fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     let cdata = colormap::colormap_data(colormap_name);
@@ -27,7 +27,8 @@ struct State {
 
     cs_pipelines: Vec<wgpu::ComputePipeline>,
     cs_vertex_buffers: Vec<wgpu::Buffer>,
-    cs_index_buffers: Vec<wgpu::Buffer>,
+    index_buffers: Vec<wgpu::Buffer>,
+    index_formats: Vec<wgpu::IndexFormat>,
     cs_uniform_buffers: Vec<wgpu::Buffer>,
     cs_bind_groups: Vec<wgpu::BindGroup>,
 
@@ -75,10 +76,6 @@ impl State {
         let cs_comp = init
             .device
             .create_shader_module(wgpu::include_wgsl!("super_shape_comp.wgsl"));
-        let cs_indices = init
-            .device
-            .create_shader_module(wgpu::include_wgsl!("../ch02/indices_comp.wgsl"));
-
         // uniform data
         let camera_position = (1.5, 1.5, 1.5).into();
         let look_direction = (0.0, 0.0, 0.0).into();
@@ -170,11 +167,7 @@ impl State {
             ],
         );
 
-        let vertex_buffer_layout = VertexBufferLayout {
-            array_stride: 48,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4], // pos, norm, col
-        };
+        let vertex_buffer_layout = IVertexData::layout(); // pos, norm, col
 
         let pipeline_layout = init
             .device
@@ -193,11 +186,7 @@ impl State {
         };
         let pipeline = ppl.new(&init);
 
-        let vertex_buffer_layout2 = VertexBufferLayout {
-            array_stride: 48,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4], // pos, norm, col2
-        };
+        let vertex_buffer_layout2 = IVertexData::layout(); // pos, norm, col2
 
         let pipeline_layout2 =
             init.device
@@ -222,75 +211,29 @@ impl State {
 
         let resol = ws::round_to_multiple(resolution, 8);
         let vertices_count = resol * resol;
-        let triangles_count = 6 * (resol - 1) * (resol - 1);
-        let lines_count = 8 * (resol - 1) * (resol - 1);
 
         println!("resolution = {}", resol);
 
-        // create compute pipeline for indices
-        let cs_index_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+        // index buffers, made once since the resolution is fixed, with 16-bit indices when the
+        // vertex count allows it
+        let (indices, indices2) = compute_grid_indices(resol);
+        let triangles_count = indices.len() as u32;
+        let lines_count = indices2.len() as u32;
+        let (index_data, index_format) = index_buffer_data(&indices);
+        let (index_data2, index_format2) = index_buffer_data(&indices2);
+
+        let index_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            size: 4 * triangles_count as u64,
-            usage: wgpu::BufferUsages::INDEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+            contents: &index_data,
+            usage: wgpu::BufferUsages::INDEX,
         });
 
-        let cs_index_buffer2 = init.device.create_buffer(&wgpu::BufferDescriptor {
+        let index_buffer2 = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index 2 Buffer"),
-            size: 4 * lines_count as u64,
-            usage: wgpu::BufferUsages::INDEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+            contents: &index_data2,
+            usage: wgpu::BufferUsages::INDEX,
         });
 
-        let cs_index_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Index Uniform Buffer"),
-            size: 4,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        init.queue
-            .write_buffer(&cs_index_uniform_buffer, 0, cast_slice(&[resol]));
-
-        let (cs_index_bind_group_layout, cs_index_bind_group) = ws::create_bind_group_storage(
-            &init.device,
-            vec![
-                wgpu::ShaderStages::COMPUTE,
-                wgpu::ShaderStages::COMPUTE,
-                wgpu::ShaderStages::COMPUTE,
-            ],
-            vec![
-                wgpu::BufferBindingType::Storage { read_only: false },
-                wgpu::BufferBindingType::Storage { read_only: false },
-                wgpu::BufferBindingType::Uniform,
-            ],
-            &[
-                cs_index_buffer.as_entire_binding(),
-                cs_index_buffer2.as_entire_binding(),
-                cs_index_uniform_buffer.as_entire_binding(),
-            ],
-        );
-
-        let cs_index_pipeline_layout =
-            init.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Compute Index Pipeline Layout"),
-                    bind_group_layouts: &[&cs_index_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
-        let cs_index_pipeline =
-            init.device
-                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                    label: Some("Compute Index Pipeline"),
-                    layout: Some(&cs_index_pipeline_layout),
-                    module: &cs_indices,
-                    entry_point: "cs_main",
-                });
-
         // create compute pipeline for simple surface
         let cs_vertex_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Vertex Buffer"),
@@ -416,11 +359,12 @@ impl State {
                 material_uniform_buffer,
             ],
 
-            cs_pipelines: vec![cs_index_pipeline, cs_pipeline],
+            cs_pipelines: vec![cs_pipeline],
             cs_vertex_buffers: vec![cs_vertex_buffer, cs_vertex_buffer2],
-            cs_index_buffers: vec![cs_index_buffer, cs_index_buffer2],
-            cs_uniform_buffers: vec![cs_index_uniform_buffer, cs_vertex_uniform_buffer],
-            cs_bind_groups: vec![cs_index_bind_group, cs_vertex_bind_group],
+            index_buffers: vec![index_buffer, index_buffer2],
+            index_formats: vec![index_format, index_format2],
+            cs_uniform_buffers: vec![cs_vertex_uniform_buffer],
+            cs_bind_groups: vec![cs_vertex_bind_group],
 
             view_mat,
             project_mat,
@@ -485,36 +429,10 @@ impl State {
                 }
                 VirtualKeyCode::LControl => {
                     self.surface_type = (self.surface_type + 1) % 9;
-                    self.scale = 1.0;
-                    if self.surface_type == 0 {
-                        self.n1 = [7.0, 0.2, 1.7, 1.7].to_vec();
-                        self.n2 = [7.0, 0.2, 1.7, 1.7].to_vec();
-                    } else if self.surface_type == 1 {
-                        self.n1 = [0.0, 1.0, 1.0, 1.0].to_vec();
-                        self.n2 = [0.0, 1.0, 1.0, 1.0].to_vec();
-                    } else if self.surface_type == 2 {
-                        self.n1 = [4.0, 1.0, 1.0, 1.0].to_vec();
-                        self.n2 = [4.0, 1.0, 1.0, 1.0].to_vec();
-                    } else if self.surface_type == 3 {
-                        self.n1 = [6.0, 1.0, 1.0, 1.0].to_vec();
-                        self.n2 = [3.0, 1.0, 1.0, 1.0].to_vec();
-                    } else if self.surface_type == 4 {
-                        self.n1 = [5.0, 0.1, 1.7, 1.7].to_vec();
-                        self.n2 = [1.0, 0.3, 0.5, 0.5].to_vec();
-                    } else if self.surface_type == 5 {
-                        self.n1 = [6.0, 0.25, 47.6, -0.66].to_vec();
-                        self.n2 = [7.0, -77.0, 0.52, -56.7].to_vec();
-                        self.scale = 0.5;
-                    } else if self.surface_type == 6 {
-                        self.n1 = [0.0, 0.48, 30.25, 0.35].to_vec();
-                        self.n2 = [5.0, 15.4, -0.45, 97.0].to_vec();
-                    } else if self.surface_type == 7 {
-                        self.n1 = [2.0, 1.0, 97.7, -0.44].to_vec();
-                        self.n2 = [7.0, -8.1, -0.09, 93.0].to_vec();
-                    } else if self.surface_type == 8 {
-                        self.n1 = [6.0, 0.77, 52.3, -0.2].to_vec();
-                        self.n2 = [5.0, 37.7, 0.53, -19.0].to_vec();
-                    }
+                    let (n1, n2, scale) = get_super_shape_preset(self.surface_type);
+                    self.n1 = n1.to_vec();
+                    self.n2 = n2.to_vec();
+                    self.scale = scale;
                     println!(
                         "i = {}, n1 = {:?}, n2 = {:?}",
                         self.surface_type, self.n1, self.n2
@@ -612,7 +530,7 @@ impl State {
             self.aspect_ratio,
         ];
         self.init.queue.write_buffer(
-            &self.cs_uniform_buffers[1],
+            &self.cs_uniform_buffers[0],
             0,
             bytemuck::cast_slice(&params),
         );
@@ -631,23 +549,13 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
-        // compute pass for indices
-        {
-            let mut cs_index_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Index Pass"),
-            });
-            cs_index_pass.set_pipeline(&self.cs_pipelines[0]);
-            cs_index_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
-            cs_index_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
-        }
-
         // compute pass for vertices
         {
             let mut cs_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: Some("Compute Pass"),
             });
-            cs_pass.set_pipeline(&self.cs_pipelines[1]);
-            cs_pass.set_bind_group(0, &self.cs_bind_groups[1], &[]);
+            cs_pass.set_pipeline(&self.cs_pipelines[0]);
+            cs_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
             cs_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
         }
 
@@ -680,8 +588,8 @@ impl State {
                 render_pass.set_pipeline(&self.pipelines[0]);
                 render_pass.set_vertex_buffer(0, self.cs_vertex_buffers[0].slice(..));
                 render_pass.set_index_buffer(
-                    self.cs_index_buffers[0].slice(..),
-                    wgpu::IndexFormat::Uint32,
+                    self.index_buffers[0].slice(..),
+                    self.index_formats[0],
                 );
                 render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
                 render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
@@ -692,8 +600,8 @@ impl State {
                 render_pass.set_pipeline(&self.pipelines[1]);
                 render_pass.set_vertex_buffer(0, self.cs_vertex_buffers[1].slice(..));
                 render_pass.set_index_buffer(
-                    self.cs_index_buffers[1].slice(..),
-                    wgpu::IndexFormat::Uint32,
+                    self.index_buffers[1].slice(..),
+                    self.index_formats[1],
                 );
                 render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
                 render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
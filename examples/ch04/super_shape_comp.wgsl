@@ -36,11 +36,20 @@ var<private> vmax:f32;
 var<private> du:f32;
 var<private> dv:f32;
 
+// the two terms are added in log space, for the large exponents of some presets they overflow
+// on their own even where the radius is of order 1
+fn superShapeRadius(angle:f32, a:vec2f, n:vec4f) -> f32 {
+    let la = n.z * log(abs(cos(n.x * angle / 4.0) / a.x));
+    let lb = n.w * log(abs(sin(n.x * angle / 4.0) / a.y));
+    let lm = max(la, lb);
+    var lr = lm;
+    if (abs(lm) < 1e30) { lr = lm + log(exp(la - lm) + exp(lb - lm)); }
+    return exp(-lr / n.y);
+}
+
 fn superShape3D(u:f32, v:f32, t:f32, n1:vec4f, n2:vec4f, a1:vec2f, a2:vec2f) -> vec3f {
-    var raux1 = pow(abs(1.0 / a1.x * cos(n1.x * u /4.0)), n1.z) + pow(abs(1.0 / a1.y * sin(n1.x * u /4.0)), n1.w);
-    var r1 = pow(abs(raux1), -1.0 / n1.y);
-    var raux2 = pow(abs(1.0 / a2.x * cos(n2.x * v /4.0)), n2.z) + pow(abs(1.0 / a2.y * sin(n2.x * v /4.0)), n2.w);
-    var r2 = pow(abs(raux2), -1.0 / n2.y);
+    var r1 = superShapeRadius(u, a1, n1);
+    var r2 = superShapeRadius(v, a2, n2);
 
     var a = 0.334*(2.0 + sin(t)); 
     var v1 = v*a;
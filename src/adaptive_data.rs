@@ -0,0 +1,273 @@
+#![allow(dead_code)]
+use cgmath::*;
+use std::collections::HashMap;
+use super::surface_data::{ISurfaceOutput, IParametricSurface, get_surface_periodicity, unique_segments, tangent_frame};
+
+// quadtree over the (u, v) domain. coordinates are integers on the finest level, where a base cell
+// of the u_resolution x v_resolution grid is 2^max_depth units wide, and each leaf is keyed by its
+// (u, v) corner with its size as the value.
+struct IQuadtree {
+    leaves: HashMap<(i64, i64), i64>,
+    nu: i64, // domain size in finest units
+    nv: i64,
+    seams: [u32; 2],
+}
+
+impl IQuadtree {
+    // the cell (cu, cv) of size 1 moved into the domain across the glued seams
+    fn wrap_cell(&self, cu:i64, cv:i64) -> Option<(i64, i64)> {
+        let (mut cu, mut cv) = (cu, cv);
+        if cu < 0 || cu >= self.nu {
+            if self.seams[0] == 0 {
+                return None;
+            }
+            cu = cu.rem_euclid(self.nu);
+            cv = match self.seams[0] { 2 => self.nv - 1 - cv, 3 => (3 * self.nv / 2 - 1 - cv).rem_euclid(self.nv), _ => cv };
+        }
+        if cv < 0 || cv >= self.nv {
+            if self.seams[1] == 0 {
+                return None;
+            }
+            cv = cv.rem_euclid(self.nv);
+            if self.seams[1] == 2 {
+                cu = self.nu - 1 - cu;
+            }
+        }
+        Some((cu, cv))
+    }
+
+    // the grid point (iu, iv) moved onto its first copy across the glued seams, like periodic_grid_indices
+    fn wrap_point(&self, iu:i64, iv:i64) -> (i64, i64) {
+        let wrap_u = |iu:i64, iv:i64| if iu == self.nu && self.seams[0] > 0 {
+            (0, match self.seams[0] { 2 => self.nv - iv, 3 => (3 * self.nv / 2 - iv) % self.nv, _ => iv })
+        } else { (iu, iv) };
+        let (iu, iv) = wrap_u(iu, iv);
+        let (iu, iv) = if iv == self.nv && self.seams[1] > 0 {
+            (if self.seams[1] == 2 { self.nu - iu } else { iu }, 0)
+        } else { (iu, iv) };
+        wrap_u(iu, iv)
+    }
+
+    // size of the leaf holding the cell (cu, cv), or None outside the domain
+    fn leaf_size(&self, cu:i64, cv:i64, top:i64) -> Option<i64> {
+        let (cu, cv) = self.wrap_cell(cu, cv)?;
+        let mut s = top;
+        while s >= 1 {
+            if self.leaves.get(&(cu - cu % s, cv - cv % s)) == Some(&s) {
+                return Some(s);
+            }
+            s /= 2;
+        }
+        None
+    }
+
+    fn split(&mut self, u:i64, v:i64, s:i64) -> [(i64, i64, i64); 4] {
+        let h = s / 2;
+        self.leaves.remove(&(u, v));
+        let children = [(u, v, h), (u, v + h, h), (u + h, v + h, h), (u + h, v, h)];
+        for c in children {
+            self.leaves.insert((c.0, c.1), h);
+        }
+        children
+    }
+
+    // cells just outside each side of a leaf, in the order of its boundary polygon: the -u side going
+    // up in v, the +v side, the +u side going down and the -v side. k runs over [0, s) along the side.
+    fn outside(u:i64, v:i64, s:i64, side:usize, k:i64) -> (i64, i64) {
+        match side {
+            0 => (u - 1, v + k),
+            1 => (u + k, v + s),
+            2 => (u + s, v + s - 1 - k),
+            _ => (u + s - 1 - k, v - 1),
+        }
+    }
+}
+
+impl IParametricSurface {
+    // quadtree tessellation of f. every cell of the base grid is split until its chordal deviation
+    // (adaptive = 1) or the angle between its normals (adaptive = 2) is within tolerance, or until
+    // max_depth. the tree is balanced so that neighbouring leaves differ by one level at most, and a
+    // leaf with a finer neighbour is drawn as a fan around its center through the hanging midpoints.
+    pub(crate) fn adaptive_surface_data(&mut self, f:&dyn Fn(f32, f32) -> [f32; 3]) -> ISurfaceOutput {
+        let top = 1i64 << self.max_depth.min(16);
+        let seams = if self.weld_seams { get_surface_periodicity(self.surface_type) } else { [0, 0] };
+        let (nu, nv) = (self.u_resolution as i64 * top, self.v_resolution as i64 * top);
+        let seams = if seams[0] == 3 && nv % 2 == 1 { [0, seams[1]] } else { seams };
+        let mut tree = IQuadtree { leaves: HashMap::new(), nu, nv, seams };
+
+        let du = (self.umax - self.umin) / nu as f32;
+        let dv = (self.vmax - self.vmin) / nv as f32;
+        let (epsu, epsv) = (0.01 * du, 0.01 * dv);
+        let param = |iu:i64, iv:i64| (self.umin + du * iu as f32, self.vmin + dv * iv as f32);
+        let derivatives = |u:f32, v:f32| {
+            let nu = Vector3::from(f(u + epsu, v)) - Vector3::from(f(u - epsu, v));
+            let nv = Vector3::from(f(u, v + epsv)) - Vector3::from(f(u, v - epsv));
+            (nu, nv)
+        };
+        let normal = |u:f32, v:f32| {
+            let (nu, nv) = derivatives(u, v);
+            nu.cross(nv).normalize()
+        };
+        let mut samples: HashMap<(i64, i64), Vector3<f32>> = HashMap::new();
+        let mut sample = |iu:i64, iv:i64| *samples.entry((iu, iv)).or_insert_with(|| {
+            let (u, v) = param(iu, iv);
+            Vector3::from(f(u, v))
+        });
+
+        // the tolerance on the chordal deviation is relative to the size of the base grid
+        let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for iu in (0..=nu).step_by(top as usize) {
+            for iv in (0..=nv).step_by(top as usize) {
+                let p = sample(iu, iv);
+                if p.x.is_finite() && p.y.is_finite() && p.z.is_finite() {
+                    for k in 0..3 {
+                        pmin[k] = pmin[k].min(p[k]);
+                        pmax[k] = pmax[k].max(p[k]);
+                    }
+                }
+                if iu < nu && iv < nv {
+                    tree.leaves.insert((iu, iv), top);
+                }
+            }
+        }
+        let chord_tol = self.tolerance * (pmax[0] - pmin[0]).max(pmax[1] - pmin[1]).max(pmax[2] - pmin[2]);
+
+        // refine
+        let mut stack:Vec<(i64, i64, i64)> = tree.leaves.iter().map(|(&(u, v), &s)| (u, v, s)).collect();
+        while let Some((u, v, s)) = stack.pop() {
+            if s == 1 {
+                continue;
+            }
+            let h = s / 2;
+            let corners = [sample(u, v), sample(u, v + s), sample(u + s, v + s), sample(u + s, v)];
+            let mids = [sample(u, v + h), sample(u + h, v + s), sample(u + s, v + h), sample(u + h, v)];
+            let center = sample(u + h, v + h);
+            let finite = corners.iter().chain(mids.iter()).chain([center].iter())
+                .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite());
+            let split = if !finite {
+                true
+            } else if self.adaptive == 2 {
+                let (uc, vc) = param(u + h, v + h);
+                let nc = normal(uc, vc);
+                [(u, v), (u, v + s), (u + s, v + s), (u + s, v)].iter().any(|&(iu, iv)| {
+                    let (pu, pv) = param(iu, iv);
+                    let angle = normal(pu, pv).dot(nc).clamp(-1.0, 1.0).acos();
+                    angle.is_nan() || angle > self.angle_tolerance
+                })
+            } else {
+                let mut deviation = (center - (corners[0] + corners[1] + corners[2] + corners[3]) * 0.25).magnitude();
+                for k in 0..4 {
+                    deviation = deviation.max((mids[k] - (corners[k] + corners[(k + 1) % 4]) * 0.5).magnitude());
+                }
+                deviation > chord_tol
+            };
+            if split {
+                stack.extend(tree.split(u, v, s));
+            }
+        }
+
+        // balance: split the leaves next to a leaf more than one level finer until there are none
+        loop {
+            let leaves:Vec<(i64, i64, i64)> = tree.leaves.iter().map(|(&(u, v), &s)| (u, v, s)).collect();
+            let mut changed = false;
+            for (u, v, s) in leaves {
+                if s < 4 || tree.leaves.get(&(u, v)) != Some(&s) {
+                    continue;
+                }
+                let unbalanced = (0..4).any(|side| (0..4).any(|k| {
+                    let (cu, cv) = IQuadtree::outside(u, v, s, side, k * s / 4);
+                    tree.leaf_size(cu, cv, top).is_some_and(|t| t < s / 2)
+                }));
+                if unbalanced {
+                    tree.split(u, v, s);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // triangles, and wireframe segments along the leaf sides
+        let mut out = ISurfaceOutput::default();
+        let mut vertices: HashMap<(i64, i64), u32> = HashMap::new();
+        let mut vertex = |out:&mut ISurfaceOutput, iu:i64, iv:i64| {
+            let key = tree.wrap_point(iu, iv);
+            *vertices.entry(key).or_insert_with(|| {
+                let (u, v) = param(key.0, key.1);
+                let (nu, nv) = derivatives(u, v);
+                let n = nu.cross(nv).normalize();
+                out.positions.push(f(u, v));
+                out.normals.push(n.into());
+                out.tangents.push(tangent_frame(nu, nv, n));
+                out.uvs.push([self.uv_lens[0] * (u - self.umin) / (self.umax - self.umin),
+                    self.uv_lens[1] * (v - self.vmin) / (self.vmax - self.vmin)]);
+                out.positions.len() as u32 - 1
+            })
+        };
+        let mut leaves:Vec<(i64, i64, i64)> = tree.leaves.iter().map(|(&(u, v), &s)| (u, v, s)).collect();
+        leaves.sort();
+        let mut lines: Vec<u32> = vec![];
+        for (u, v, s) in leaves {
+            let corners = [(u, v), (u, v + s), (u + s, v + s), (u + s, v)];
+            let mut ring: Vec<u32> = vec![];
+            let mut hanging = false;
+            for side in 0..4 {
+                ring.push(vertex(&mut out, corners[side].0, corners[side].1));
+                let (cu, cv) = IQuadtree::outside(u, v, s, side, s / 2);
+                if s > 1 && tree.leaf_size(cu, cv, top).is_some_and(|t| t < s) {
+                    let (a, b) = (corners[side], corners[(side + 1) % 4]);
+                    ring.push(vertex(&mut out, (a.0 + b.0) / 2, (a.1 + b.1) / 2));
+                    hanging = true;
+                }
+            }
+            for k in 0..ring.len() {
+                lines.extend([ring[k], ring[(k + 1) % ring.len()]]);
+            }
+            if hanging {
+                let c = vertex(&mut out, u + s / 2, v + s / 2);
+                for k in 0..ring.len() {
+                    out.indices.extend([c, ring[k], ring[(k + 1) % ring.len()]]);
+                }
+            } else {
+                out.indices.extend([ring[0], ring[1], ring[2], ring[2], ring[3], ring[0]]);
+            }
+        }
+        out.indices2 = unique_segments(lines.into_iter());
+
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.normalize_positions(self.scale);
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the cells on either side of a glued seam are refined independently, so the hanging vertices must
+    // still meet there: no edge of a closed surface may be left with a single triangle
+    #[test]
+    fn glued_seams_stay_closed() {
+        // (surface type, chi): klein_bottle, klein_bottle2, torus, boy_shape, figure8
+        for (key, chi) in [(0, 0), (11, 0), (21, 0), (5, 1), (8, 0)] {
+            for adaptive in [1, 2] {
+                let mut surface = IParametricSurface { surface_type: key, u_resolution: 16, v_resolution: 16,
+                    adaptive, max_depth: 3, weld_seams: true, ..Default::default() };
+                let out = surface.new();
+                let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+                for t in out.indices.chunks(3) {
+                    for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                        *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+                    }
+                }
+                assert!(edges.values().all(|&n| n == 2), "surface type {}, mode {}", key, adaptive);
+                assert!(out.indices.len() > 6 * 16 * 16, "surface type {}, mode {}: not refined", key, adaptive);
+                let stats = out.mesh_stats();
+                assert_eq!((stats.euler_characteristic, stats.boundary_loops), (chi, 0), "surface type {}", key);
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+#![allow(dead_code)]
+use cgmath::*;
+use super::colormap;
+use super::surface_data::{ISurfaceOutput, grid_indices, add_cap};
+
+// any unit vector perpendicular to the unit vector a
+pub(crate) fn perpendicular(a:Vector3<f32>) -> Vector3<f32> {
+    let b = if a.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+    (b - a * a.dot(b)).normalize()
+}
+
+// a sampled curve is closed when its last point coincides with its first point
+pub(crate) fn is_closed(pts:&[Vector3<f32>]) -> bool {
+    let len:f32 = pts.windows(2).map(|w| (w[1] - w[0]).magnitude()).sum();
+    pts.len() > 2 && (pts[pts.len() - 1] - pts[0]).magnitude() <= 1e-5 * len
+}
+
+// unit tangents from central differences, wrapping around the seam of a closed curve
+fn curve_tangents(pts:&[Vector3<f32>], closed:bool) -> Vec<Vector3<f32>> {
+    let n = pts.len();
+    let mut tangents: Vec<Vector3<f32>> = vec![];
+    for i in 0..n {
+        let t = if closed && (i == 0 || i == n - 1) {
+            pts[1] - pts[n - 2]
+        } else if i == 0 {
+            pts[1] - pts[0]
+        } else if i == n - 1 {
+            pts[n - 1] - pts[n - 2]
+        } else {
+            pts[i + 1] - pts[i - 1]
+        };
+        tangents.push(if t.magnitude2() > 0.0 { t.normalize() } else { Vector3::unit_x() });
+    }
+    tangents
+}
+
+// rotation-minimizing frames (t, n, b) along a sampled curve using the double reflection method.
+// for a closed curve (last point == first point) the residual twist is distributed along the curve.
+pub fn rotation_minimizing_frames(pts:&[Vector3<f32>], closed:bool)
+-> Vec<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+    let n = pts.len();
+    let tangents = curve_tangents(pts, closed);
+
+    let mut normals = vec![perpendicular(tangents[0])];
+    for i in 0..n - 1 {
+        let r = normals[i];
+        let v1 = pts[i + 1] - pts[i];
+        let c1 = v1.dot(v1);
+        if c1 < 1e-12 {
+            normals.push(r);
+            continue;
+        }
+        let rl = r - v1 * (2.0 / c1 * v1.dot(r));
+        let tl = tangents[i] - v1 * (2.0 / c1 * v1.dot(tangents[i]));
+        let v2 = tangents[i + 1] - tl;
+        let c2 = v2.dot(v2);
+        let r1 = if c2 < 1e-12 { rl } else { rl - v2 * (2.0 / c2 * v2.dot(rl)) };
+        // remove the drift from the tangent
+        normals.push((r1 - tangents[i + 1] * r1.dot(tangents[i + 1])).normalize());
+    }
+
+    if closed && n > 2 {
+        let (t0, r0, rn) = (tangents[0], normals[0], normals[n - 1]);
+        let angle = rn.cross(r0).dot(t0).atan2(rn.dot(r0));
+        for i in 0..n {
+            let rot = Matrix3::from_axis_angle(tangents[i], Rad(angle * i as f32 / (n - 1) as f32));
+            normals[i] = rot * normals[i];
+        }
+    }
+
+    (0..n).map(|i| (tangents[i], normals[i], tangents[i].cross(normals[i]))).collect()
+}
+
+// Frenet frames (t, n, b) along a sampled curve. on straight pieces, where the curvature vanishes,
+// the previous normal is carried along.
+pub fn frenet_frames(pts:&[Vector3<f32>], closed:bool) -> Vec<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+    let n = pts.len();
+    let tangents = curve_tangents(pts, closed);
+
+    let mut normals: Vec<Vector3<f32>> = vec![];
+    for i in 0..n {
+        let dt = if closed && (i == 0 || i == n - 1) {
+            tangents[1] - tangents[n - 2]
+        } else {
+            tangents[(i + 1).min(n - 1)] - tangents[i.saturating_sub(1)]
+        };
+        let k = dt - tangents[i] * dt.dot(tangents[i]);
+        let normal = if k.magnitude2() > 1e-12 {
+            k.normalize()
+        } else if i > 0 {
+            let r = normals[i - 1];
+            (r - tangents[i] * r.dot(tangents[i])).normalize()
+        } else {
+            perpendicular(tangents[0])
+        };
+        normals.push(normal);
+    }
+
+    (0..n).map(|i| (tangents[i], normals[i], tangents[i].cross(normals[i]))).collect()
+}
+
+// region: tube curve
+pub struct ITubeCurve {
+    pub f: Box<dyn Fn(f32) -> [f32; 3]>,
+    pub radius: Box<dyn Fn(f32) -> f32>,  // tube radius along t
+    pub tmin: f32,
+    pub tmax: f32,
+    pub t_resolution: u16,
+    pub tube_resolution: u16, // sides of the cross section, at least 3
+    pub frame_type: u32, // 0: rotation-minimizing frames, 1: Frenet frames
+    pub caps: bool,
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_source: u32, // 0: arc length, 1: speed, 2: x-direction, 3: y-direction, 4: z-direction
+    pub uv_lens: [f32; 2],
+}
+
+impl Default for ITubeCurve {
+    fn default() -> Self {
+        Self {
+            f: Box::new(super::math_func::trefoil),
+            radius: Box::new(|_| 0.3),
+            tmin: 0.0,
+            tmax: 2.0 * std::f32::consts::PI,
+            t_resolution: 200,
+            tube_resolution: 24,
+            frame_type: 0,
+            caps: true,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_source: 0,
+            uv_lens: [1.0, 1.0],
+        }
+    }
+}
+
+impl ITubeCurve {
+    pub fn new(&mut self) -> ISurfaceOutput {
+        // fewer than 3 sides leave no ring for the caps to close
+        let (nu, nv) = ((self.t_resolution as usize).max(1), (self.tube_resolution as usize).max(3));
+        let dt = (self.tmax - self.tmin) / nu as f32;
+        let eps = 0.01 * dt;
+
+        let mut pts: Vec<Vector3<f32>> = vec![];
+        let mut radii: Vec<f32> = vec![];
+        let mut speeds: Vec<f32> = vec![];
+        for i in 0..=nu {
+            let t = self.tmin + dt * i as f32;
+            pts.push(Vector3::from((self.f)(t)));
+            radii.push((self.radius)(t));
+            speeds.push((Vector3::from((self.f)(t + eps)) - Vector3::from((self.f)(t - eps))).magnitude() / (2.0 * eps));
+        }
+
+        let closed = is_closed(&pts);
+        let frames = if self.frame_type == 1 { frenet_frames(&pts, closed) }
+            else { rotation_minimizing_frames(&pts, closed) };
+
+        // arc length along the curve
+        let mut lens = vec![0.0f32];
+        for i in 1..=nu {
+            lens.push(lens[i - 1] + (pts[i] - pts[i - 1]).magnitude());
+        }
+        let total = lens[nu].max(f32::EPSILON);
+
+        let mut out = ISurfaceOutput::default();
+        let mut values: Vec<f32> = vec![];
+        for i in 0..=nu {
+            let (t, n, b) = frames[i];
+            // the slope of the radius tilts the normals of a tube with variable radius
+            let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nu));
+            let dr = if lens[i1] > lens[i0] { (radii[i1] - radii[i0]) / (lens[i1] - lens[i0]) } else { 0.0 };
+            for j in 0..=nv {
+                let theta = 2.0 * std::f32::consts::PI * j as f32 / nv as f32;
+                let radial = n * theta.cos() + b * theta.sin();
+                let pos = pts[i] + radial * radii[i];
+                out.positions.push(pos.into());
+                out.normals.push((radial - t * dr).normalize().into());
+                out.uvs.push([self.uv_lens[0] * lens[i] / total, self.uv_lens[1] * j as f32 / nv as f32]);
+                values.push(match self.colormap_source {
+                    0 => lens[i],
+                    1 => speeds[i],
+                    k => pos[(k as usize - 2).min(2)],
+                });
+            }
+        }
+        (out.indices, out.indices2) = grid_indices(nu as u32, nv as u32);
+
+        if self.caps && !closed {
+            let first = out.positions[0..=nv].to_vec();
+            let last = out.positions[nu * (nv + 1)..].to_vec();
+            add_cap(&mut out, &first, -frames[0].0);
+            values.extend(vec![values[0]; nv + 2]);
+            add_cap(&mut out, &last, frames[nu].0);
+            values.extend(vec![values[nu * (nv + 1)]; nv + 2]);
+        }
+        out.reject_non_finite();
+
+        out.normalize_positions(self.scale);
+
+        // colormap
+        let min_val = values.iter().cloned().filter(|v| v.is_finite()).fold(f32::MAX, f32::min);
+        let max_val = values.iter().cloned().filter(|v| v.is_finite()).fold(f32::MIN, f32::max).max(min_val + f32::EPSILON);
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        out.colors = values.iter().map(|&v| colormap::color_lerp(cdata, min_val, max_val, v)).collect();
+        out.colors2 = values.iter().map(|&v| colormap::color_lerp(cdata2, min_val, max_val, v)).collect();
+        out.remove_unused_vertices();
+        out.compute_tangents();
+        out
+    }
+}
+// endregion: tube curve
@@ -0,0 +1,299 @@
+#![allow(dead_code)]
+use image::GenericImageView;
+use std::path::Path;
+
+// region: height grid
+// regularly sampled heights over a rectangular x-z domain. heights[i + j * nx] is the height
+// at x = xmin + i * (xmax - xmin)/(nx - 1), z = zmin + j * (zmax - zmin)/(nz - 1); NaN marks no data.
+pub struct IHeightGrid {
+    pub nx: usize,
+    pub nz: usize,
+    pub heights: Vec<f32>,
+    pub xmin: f32,
+    pub xmax: f32,
+    pub zmin: f32,
+    pub zmax: f32,
+}
+
+impl IHeightGrid {
+    // grayscale or 16-bit heightmap (PNG, TIFF, ...). heights are scaled to [0, 1] and placed over
+    // the [-1, 1] x [-1, 1] domain; image columns run along x and image rows along z.
+    pub fn from_image<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let img = image::open(path)?;
+        let (nx, nz) = (img.width() as usize, img.height() as usize);
+        if nx < 2 || nz < 2 {
+            anyhow::bail!("heightmap must be at least 2x2 pixels, got {}x{}", nx, nz);
+        }
+        // to_luma16 widens 8 bits by a shift, which would leave white short of 1
+        let color = img.color();
+        let heights = if color.bytes_per_pixel() > color.channel_count() {
+            img.to_luma16().pixels().map(|p| p.0[0] as f32 / u16::MAX as f32).collect()
+        } else {
+            img.to_luma8().pixels().map(|p| p.0[0] as f32 / u8::MAX as f32).collect()
+        };
+        Ok(Self { nx, nz, heights, xmin: -1.0, xmax: 1.0, zmin: -1.0, zmax: 1.0 })
+    }
+
+    // ESRI ASCII grid (.asc). NODATA cells are stored as NaN so they become holes in the surface.
+    // x runs east and z runs north, with the first data row of the file at zmax.
+    pub fn from_esri_ascii<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut tokens = text.split_whitespace().peekable();
+        let (mut ncols, mut nrows, mut cellsize) = (0usize, 0usize, 0.0f32);
+        let (mut xll, mut yll, mut center) = (0.0f32, 0.0f32, false);
+        let mut nodata: Option<f32> = None;
+
+        while let Some(key) = tokens.next_if(|t| t.parse::<f32>().is_err()) {
+            let value = tokens.next().ok_or_else(|| anyhow::anyhow!("missing value for {}", key))?;
+            match key.to_lowercase().as_str() {
+                "ncols" => ncols = value.parse()?,
+                "nrows" => nrows = value.parse()?,
+                "xllcorner" => xll = value.parse()?,
+                "yllcorner" => yll = value.parse()?,
+                "xllcenter" => { xll = value.parse()?; center = true; },
+                "yllcenter" => { yll = value.parse()?; center = true; },
+                "cellsize" => cellsize = value.parse()?,
+                "nodata_value" => nodata = Some(value.parse()?),
+                _ => anyhow::bail!("unknown ESRI ASCII grid header field {}", key),
+            }
+        }
+        if ncols < 2 || nrows < 2 || cellsize <= 0.0 {
+            anyhow::bail!("invalid ESRI ASCII grid header: ncols = {}, nrows = {}, cellsize = {}", ncols, nrows, cellsize);
+        }
+
+        let values = tokens.map(|t| t.parse::<f32>()).collect::<Result<Vec<f32>, _>>()?;
+        if values.len() != ncols * nrows {
+            anyhow::bail!("expected {} grid values, found {}", ncols * nrows, values.len());
+        }
+
+        let mut heights = vec![f32::NAN; ncols * nrows];
+        for (k, v) in values.iter().enumerate() {
+            let (i, row) = (k % ncols, k / ncols);
+            if nodata != Some(*v) {
+                heights[i + (nrows - 1 - row) * ncols] = *v;
+            }
+        }
+
+        let offset = if center { 0.0 } else { 0.5 * cellsize };
+        let (xmin, zmin) = (xll + offset, yll + offset);
+        Ok(Self {
+            nx: ncols, nz: nrows, heights,
+            xmin, xmax: xmin + (ncols - 1) as f32 * cellsize,
+            zmin, zmax: zmin + (nrows - 1) as f32 * cellsize,
+        })
+    }
+
+    // regular XYZ text grid with one "x y z" point per line (spaces, tabs or commas). the file's y
+    // becomes z and its z the height; missing grid points and values equal to nodata become holes.
+    pub fn from_xyz<P: AsRef<Path>>(path: P, nodata: Option<f32>) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut pts: Vec<[f32; 3]> = vec![];
+        for line in text.lines() {
+            let values: Vec<f32> = line.split(|c:char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty())
+                .map_while(|t| t.parse().ok())
+                .collect();
+            // skip header and comment lines
+            if values.len() >= 3 {
+                pts.push([values[0], values[1], values[2]]);
+            }
+        }
+
+        let xs = grid_coordinates(pts.iter().map(|p| p[0]).collect());
+        let zs = grid_coordinates(pts.iter().map(|p| p[1]).collect());
+        let (nx, nz) = (xs.len(), zs.len());
+        if nx < 2 || nz < 2 {
+            anyhow::bail!("XYZ data does not form a grid: {} x {} distinct coordinates", nx, nz);
+        }
+
+        let mut heights = vec![f32::NAN; nx * nz];
+        for p in pts.iter() {
+            if nodata != Some(p[2]) {
+                heights[nearest_index(&xs, p[0]) + nearest_index(&zs, p[1]) * nx] = p[2];
+            }
+        }
+        Ok(Self { nx, nz, heights, xmin: xs[0], xmax: xs[nx - 1], zmin: zs[0], zmax: zs[nz - 1] })
+    }
+
+    // range of the finite heights, an error when the grid holds no data at all
+    pub fn height_range(&self) -> anyhow::Result<(f32, f32)> {
+        let mut hmin = f32::MAX;
+        let mut hmax = f32::MIN;
+        for h in self.heights.iter().filter(|h| h.is_finite()) {
+            hmin = hmin.min(*h);
+            hmax = hmax.max(*h);
+        }
+        if hmin > hmax {
+            anyhow::bail!("height grid has no finite heights");
+        }
+        Ok((hmin, hmax))
+    }
+
+    fn at(&self, i:isize, j:isize) -> f32 {
+        let i = i.clamp(0, self.nx as isize - 1) as usize;
+        let j = j.clamp(0, self.nz as isize - 1) as usize;
+        self.heights[i + j * self.nx]
+    }
+
+    // height at (x, z), clamped to the grid domain. filter 0: bilinear, 1: bicubic (Catmull-Rom)
+    pub fn sample(&self, x:f32, z:f32, filter:u32) -> f32 {
+        let fx = ((x - self.xmin) / (self.xmax - self.xmin)).clamp(0.0, 1.0) * (self.nx - 1) as f32;
+        let fz = ((z - self.zmin) / (self.zmax - self.zmin)).clamp(0.0, 1.0) * (self.nz - 1) as f32;
+        let (i, j) = (fx.floor() as isize, fz.floor() as isize);
+        let (tx, tz) = (fx - i as f32, fz - j as f32);
+
+        let h0 = lerp(self.at(i, j), self.at(i + 1, j), tx);
+        let h1 = lerp(self.at(i, j + 1), self.at(i + 1, j + 1), tx);
+        let bilinear = lerp(h0, h1, tz);
+
+        if filter == 1 {
+            let mut rows = [0.0f32; 4];
+            for (k, row) in rows.iter_mut().enumerate() {
+                let jj = j - 1 + k as isize;
+                *row = catmull_rom(self.at(i - 1, jj), self.at(i, jj), self.at(i + 1, jj), self.at(i + 2, jj), tx);
+            }
+            let bicubic = catmull_rom(rows[0], rows[1], rows[2], rows[3], tz);
+            // next to a hole only the bilinear neighbourhood has to be valid
+            if bicubic.is_finite() { bicubic } else { bilinear }
+        } else {
+            bilinear
+        }
+    }
+}
+
+// sorted distinct coordinates, merging values closer than a millionth of their range
+fn grid_coordinates(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let tol = 1e-6 * (values.last().unwrap_or(&0.0) - values.first().unwrap_or(&0.0)).abs();
+    let mut coords: Vec<f32> = vec![];
+    for v in values {
+        if !matches!(coords.last(), Some(c) if v - c <= tol) {
+            coords.push(v);
+        }
+    }
+    coords
+}
+
+fn nearest_index(coords:&[f32], v:f32) -> usize {
+    let k = coords.partition_point(|c| *c < v);
+    if k == 0 {
+        0
+    } else if k == coords.len() || v - coords[k - 1] < coords[k] - v {
+        k - 1
+    } else {
+        k
+    }
+}
+
+// a sample lying exactly on a grid line must not pick up a hole on the other side
+fn lerp(a:f32, b:f32, t:f32) -> f32 {
+    if t == 0.0 { a } else { a + (b - a) * t }
+}
+
+fn catmull_rom(p0:f32, p1:f32, p2:f32, p3:f32, t:f32) -> f32 {
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t +
+        (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+}
+// endregion: height grid
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name:&str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("wgpu_gpu_surfaces_{}_{}", std::process::id(), name))
+    }
+
+    // load text written to a temporary file
+    fn load<T>(name:&str, text:&str, f:impl Fn(std::path::PathBuf) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let path = temp_path(name);
+        std::fs::write(&path, text).unwrap();
+        let result = f(path.clone());
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn images_of_8_and_16_bits() {
+        let path = temp_path("heights8.png");
+        image::GrayImage::from_fn(3, 2, |x, y| image::Luma([(x * 100 + y * 55) as u8])).save(&path).unwrap();
+        let grid = IHeightGrid::from_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((grid.nx, grid.nz), (3, 2));
+        // 8-bit and 16-bit images both span [0, 1]
+        assert_eq!(grid.heights[2 + 3], 1.0);
+        assert_eq!(grid.heights[1], 100.0 / 255.0);
+
+        let path = temp_path("heights16.png");
+        image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_fn(2, 2, |x, y| image::Luma([(x * 1000 + y * 64535) as u16]))
+            .save(&path).unwrap();
+        let grid = IHeightGrid::from_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(grid.heights, vec![0.0, 1000.0 / 65535.0, 64535.0 / 65535.0, 1.0]);
+        assert_eq!((grid.xmin, grid.xmax, grid.zmin, grid.zmax), (-1.0, 1.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn esri_ascii_grids() {
+        let text = "NCOLS 3\nnrows 2\nxllcorner 10\nYLLCORNER 20\ncellsize 2\nNODATA_value -9999\n1 2 3\n4 -9999 6\n";
+        let grid = load("grid.asc", text, IHeightGrid::from_esri_ascii).unwrap();
+        assert_eq!((grid.nx, grid.nz), (3, 2));
+        // the first row of the file is the northern one, at zmax
+        assert_eq!(&grid.heights[3..], &[1.0, 2.0, 3.0]);
+        assert_eq!(grid.heights[0], 4.0);
+        assert!(grid.heights[1].is_nan());
+        // corners are half a cell outside the samples, centers are on them
+        assert_eq!((grid.xmin, grid.xmax, grid.zmin, grid.zmax), (11.0, 15.0, 21.0, 23.0));
+        let centered = load("center.asc", &text.replace("xllcorner", "xllcenter").replace("YLLCORNER", "yllcenter"),
+            IHeightGrid::from_esri_ascii).unwrap();
+        assert_eq!((centered.xmin, centered.zmin), (10.0, 20.0));
+        assert_eq!(grid.height_range().unwrap(), (1.0, 6.0));
+
+        for bad in [text.replace("cellsize", "cellwidth"), text.replace("6\n", ""), text.replace("nrows 2", "nrows 1"),
+            text.replace("cellsize 2", "cellsize")] {
+            assert!(load("bad.asc", &bad, IHeightGrid::from_esri_ascii).is_err(), "{}", bad);
+        }
+        // a grid without any data has no height range to normalize with
+        let empty = load("empty.asc", "ncols 2\nnrows 2\ncellsize 1\nnodata_value 0\n0 0\n0 0\n", IHeightGrid::from_esri_ascii).unwrap();
+        assert!(empty.height_range().is_err());
+        let mut surface = super::super::surface_data::ISimpleSurface::default();
+        assert!(surface.height_grid_data(&empty, 0, 1.0).is_err());
+    }
+
+    #[test]
+    fn xyz_grids() {
+        let text = "x,y,z\n0,0,1\n1,0,2\n2,0,3\n0 1 4\n2\t1\t-1\n# comment\n";
+        let grid = load("grid.xyz", text, |p| IHeightGrid::from_xyz(p, Some(-1.0))).unwrap();
+        assert_eq!((grid.nx, grid.nz), (3, 2));
+        assert_eq!(&grid.heights[..4], &[1.0, 2.0, 3.0, 4.0]);
+        // the missing point and the nodata one are holes
+        assert!(grid.heights[4].is_nan() && grid.heights[5].is_nan());
+        assert_eq!((grid.xmin, grid.xmax, grid.zmin, grid.zmax), (0.0, 2.0, 0.0, 1.0));
+        assert!(load("line.xyz", "0 0 1\n1 0 2\n", |p| IHeightGrid::from_xyz(p, None)).is_err());
+    }
+
+    #[test]
+    fn sampling_filters() {
+        // a ramp along x, constant along z
+        let grid = IHeightGrid { nx: 4, nz: 2, heights: vec![0.0, 1.0, 2.0, 3.0, 0.0, 1.0, 2.0, 3.0],
+            xmin: 0.0, xmax: 3.0, zmin: 0.0, zmax: 1.0 };
+        for filter in [0, 1] {
+            assert_eq!(grid.sample(2.0, 0.5, filter), 2.0);
+            // outside the domain the edge is held
+            assert_eq!(grid.sample(-1.0, 0.5, filter), 0.0);
+            assert_eq!(grid.sample(4.0, 2.0, filter), 3.0);
+        }
+        // Catmull-Rom reproduces the ramp inside, but the clamped neighbour bends it in the edge cells
+        assert_eq!(grid.sample(1.5, 0.5, 0), 1.5);
+        assert_eq!(grid.sample(1.5, 0.5, 1), 1.5);
+        assert_eq!(grid.sample(0.5, 0.5, 0), 0.5);
+        assert_eq!(grid.sample(0.5, 0.5, 1), 0.4375);
+
+        // next to a hole the bicubic filter falls back to the bilinear one
+        let mut holed = grid;
+        holed.heights[0] = f32::NAN;
+        assert!(holed.sample(0.5, 0.5, 0).is_nan());
+        assert_eq!(holed.sample(1.5, 0.0, 1), 1.5);
+        assert_eq!(holed.sample(2.0, 0.0, 1), 2.0);
+    }
+}
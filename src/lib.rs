@@ -1,4 +1,13 @@
 pub mod vertex_data;
 pub mod colormap;
 pub mod math_func;
-pub mod surface_data;
\ No newline at end of file
+pub mod surface_data;
+pub mod curve_data;
+pub mod height_data;
+pub mod scatter_data;
+pub mod nurbs_data;
+pub mod subdivision;
+pub mod minimal_data;
+pub mod mesh_stats;
+pub mod adaptive_data;
+pub mod mesh_io;
\ No newline at end of file
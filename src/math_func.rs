@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 use std::f32::consts::PI;
+use num_complex::Complex32;
 
 pub fn torus(u:f32, v:f32) -> [f32; 3] {
     let x = (1.0 + 0.3 * v.cos()) * u.cos();
@@ -112,7 +113,7 @@ pub fn klein_bottle(u:f32, v:f32) -> [f32; 3] {
 pub fn astroid(u:f32, v:f32) -> [f32; 3] {
     let a = 1.5f32;
     let x = a * (u.cos()).powf(3.0) * (v.cos()).powf(3.0);
-    let y = a * (u.sin()).powf(3.0);
+    let y = a * (v.sin()).powf(3.0);
     let z = a * (u.sin()).powf(3.0) * (v.cos()).powf(3.0);
     [x, y, z]
 }
@@ -215,6 +216,127 @@ pub fn steiner(u:f32, v:f32) -> [f32; 3] {
     [x, y, z]
 }
 
+pub fn mobius_strip(u:f32, v:f32) -> [f32; 3] {
+    let x = (1.0 + v * (0.5 * u).cos()) * u.cos();
+    let y = v * (0.5 * u).sin();
+    let z = (1.0 + v * (0.5 * u).cos()) * u.sin();
+    [x, y, z]
+}
+
+pub fn dini(u:f32, v:f32) -> [f32; 3] {
+    let (a, b) = (1.0, 0.2);
+    let x = a * u.cos() * v.sin();
+    let y = a * (v.cos() + (0.5 * v).tan().ln()) + b * u;
+    let z = a * u.sin() * v.sin();
+    [x, y, z]
+}
+
+pub fn catenoid(u:f32, v:f32) -> [f32; 3] {
+    let x = v.cosh() * u.cos();
+    let y = v;
+    let z = v.cosh() * u.sin();
+    [x, y, z]
+}
+
+pub fn helicoid(u:f32, v:f32) -> [f32; 3] {
+    let x = v * u.cos();
+    let y = u;
+    let z = v * u.sin();
+    [x, y, z]
+}
+
+// theta function theta_1(w) of the square lattice (q = e^-pi) and its first two derivatives
+fn theta1(w:Complex32) -> (Complex32, Complex32, Complex32) {
+    let zero = Complex32::new(0.0, 0.0);
+    let (mut t, mut t1, mut t2) = (zero, zero, zero);
+    for n in 0..6 {
+        let k = (2 * n + 1) as f32;
+        let c = 2.0 * (-1.0f32).powi(n) * (-PI * (n as f32 + 0.5).powi(2)).exp();
+        t += c * (k * w).sin();
+        t1 += c * k * (k * w).cos();
+        t2 -= c * k * k * (k * w).sin();
+    }
+    (t, t1, t2)
+}
+
+// Weierstrass zeta and p functions for the lattice with periods 1 and i
+fn weierstrass_zeta(z:Complex32) -> Complex32 {
+    let (t, t1, _) = theta1(PI * z);
+    PI * z + PI * t1 / t
+}
+
+fn weierstrass_p(z:Complex32) -> Complex32 {
+    let (t, t1, t2) = theta1(PI * z);
+    -PI - PI * PI * (t2 * t - t1 * t1) / (t * t)
+}
+
+// Costa's minimal surface (Gray's parametrization) over (0, 1) x (0, 1). the corners and the
+// midpoints of the edges at u = 0 and v = 0 are the three ends of the surface.
+pub fn costa(u:f32, v:f32) -> [f32; 3] {
+    let e1 = 6.875_186;  // p(1/2)
+    let z = Complex32::new(u, v);
+    let i = Complex32::new(0.0, 1.0);
+    let (zeta, zeta1, zeta2) = (weierstrass_zeta(z), weierstrass_zeta(z - 0.5), weierstrass_zeta(z - 0.5 * i));
+    let c = PI * PI / (4.0 * e1);
+    let x = 0.5 * (-zeta + PI * u + c + PI / (2.0 * e1) * (zeta1 - zeta2)).re;
+    let y = 0.5 * (-i * zeta + PI * v + c - PI / (2.0 * e1) * (i * zeta1 - i * zeta2)).re;
+    let p = weierstrass_p(z);
+    let h = 0.25 * (2.0 * PI).sqrt() * ((p - e1) / (p + e1)).norm().ln();
+    [x, h, y]
+}
+
+pub fn cross_cap(u:f32, v:f32) -> [f32; 3] {
+    let x = u.cos() * (2.0 * v).sin();
+    let y = v.cos() * v.cos() - u.cos() * u.cos() * v.sin() * v.sin();
+    let z = u.sin() * (2.0 * v).sin();
+    [x, y, z]
+}
+
+// the same surface as steiner, parametrized over the sphere so that it is covered only once
+pub fn roman(u:f32, v:f32) -> [f32; 3] {
+    let (sx, sy, sz) = (v.sin() * u.cos(), v.sin() * u.sin(), v.cos());
+    [sy * sz, sx * sy, sx * sz]
+}
+
+// region: 4d surfaces
+// the 4D surfaces below lie on the unit 3-sphere
+pub fn clifford_torus(u:f32, v:f32) -> [f32; 4] {
+    let a = 0.5f32.sqrt();
+    [a * u.cos(), a * u.sin(), a * v.cos(), a * v.sin()]
+}
+
+pub fn flat_torus(u:f32, v:f32) -> [f32; 4] {
+    let eta = PI / 6.0;
+    [eta.cos() * u.cos(), eta.cos() * u.sin(), eta.sin() * v.cos(), eta.sin() * v.sin()]
+}
+
+// Hopf torus: the Hopf fibers over a closed curve with three lobes on the 2-sphere. u moves along the
+// curve and v along the fibers.
+pub fn hopf_torus(u:f32, v:f32) -> [f32; 4] {
+    let theta = 0.5 * PI + 0.5 * (3.0 * u).sin();
+    let (c, s) = ((0.5 * theta).cos(), (0.5 * theta).sin());
+    [c * v.cos(), c * v.sin(), s * (v - u).cos(), s * (v - u).sin()]
+}
+
+// rotate a 4D point by the angles in the xy, xz, xw, yz, yw and zw planes, in that order
+pub fn rotate_4d(p:[f32; 4], angles:[f32; 6]) -> [f32; 4] {
+    let planes = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+    let mut q = p;
+    for (k, &(i, j)) in planes.iter().enumerate() {
+        let (s, c) = angles[k].sin_cos();
+        (q[i], q[j]) = (c * q[i] - s * q[j], s * q[i] + c * q[j]);
+    }
+    q
+}
+
+// projection 0: stereographic from the pole (0, 0, 0, 1), 1: perspective from (0, 0, 0, distance).
+// the denominator is kept away from zero so that points at the pole stay finite.
+pub fn project_4d(p:[f32; 4], projection:u32, distance:f32) -> [f32; 3] {
+    let k = if projection == 1 { distance / (distance - p[3]).max(1e-3) } else { 1.0 / (1.0 - p[3]).max(1e-3) };
+    [k * p[0], k * p[1], k * p[2]]
+}
+// endregion: 4d surfaces
+
 pub fn sinc(x:f32, z:f32, t:f32) -> [f32; 3] {
     let a = 1.01 + t.sin();
     let r = a * (x*x + z*z).sqrt();
@@ -222,12 +344,13 @@ pub fn sinc(x:f32, z:f32, t:f32) -> [f32; 3] {
     [x, y, z]
 }
 
+// the peaks of simple_surface_func.wgsl, so that the cpu and gpu surfaces agree
 pub fn peaks(x:f32, z:f32, t:f32) -> [f32; 3] {
-    let a = 1.00001 + t.sin();
-    let b = 1.00001 + (1.5*t).sin();
-    let c = 1.00001 + (2.0*t).sin();    
-    let y = 3.0*(1.0-x)*(1.0-x)*(-a*(x*x)-a*(z+1.0)*(z+1.0)).exp()-
-    10.0*(x/5.0-x*x*x-z*z*z*z*z)*(-b*x*x-b*z*z).exp() - 1.0/3.0*(-c*(x+1.0)*(x+1.0)-c*z*z).exp();
+    let a = 1.0 + 0.2*t.sin();
+    let b = 1.0 + 0.2*(1.5*t).sin();
+    let c = 1.0 + 0.2*(2.0*t).sin();
+    let y = 3.0*(1.0-z)*(1.0-z)*(-a*(z*z)-a*(x+1.0)*(x+1.0)).exp()-
+    10.0*(z/5.0-z*z*z-x*x*x*x*x)*(-b*z*z-b*x*x).exp() - 1.0/3.0*(-c*(z+1.0)*(z+1.0)-c*x*x).exp();
     [z, y, x]
 }
 
@@ -236,3 +359,213 @@ pub fn poles(x:f32, z:f32, t:f32) -> [f32; 3] {
     let y =  x*z/(((x-a)*(x-a)*(x-a)).abs() + (z- 2.0*a)*(z- 2.0*a) + 2.0);
     [x, y, z]
 }
+
+pub fn monkey_saddle(x:f32, z:f32, _t:f32) -> [f32; 3] {
+    [x, x * x * x - 3.0 * x * z * z, z]
+}
+
+pub fn rosenbrock(x:f32, z:f32, _t:f32) -> [f32; 3] {
+    [x, (1.0 - x) * (1.0 - x) + 100.0 * (z - x * x) * (z - x * x), z]
+}
+
+pub fn himmelblau(x:f32, z:f32, _t:f32) -> [f32; 3] {
+    [x, (x * x + z - 11.0).powi(2) + (x + z * z - 7.0).powi(2), z]
+}
+
+pub fn ackley(x:f32, z:f32, _t:f32) -> [f32; 3] {
+    let y = -20.0 * (-0.2 * (0.5 * (x * x + z * z)).sqrt()).exp() -
+        (0.5 * ((2.0 * PI * x).cos() + (2.0 * PI * z).cos())).exp() + std::f32::consts::E + 20.0;
+    [x, y, z]
+}
+
+pub fn ripple(x:f32, z:f32, t:f32) -> [f32; 3] {
+    let r = (x * x + z * z).sqrt();
+    [x, (2.0 * r - 2.0 * t).sin() * (-0.1 * r).exp(), z]
+}
+
+// (|cos(m angle/4)/a0|^n2 + |sin(m angle/4)/a1|^n3)^(-1/n1) for n = [m, n1, n2, n3], with the two terms
+// added in log space: for the large exponents of some presets they overflow f32 on their own even
+// where the radius is of order 1
+fn super_shape_radius(angle:f32, a:[f32; 2], n:[f32; 4]) -> f32 {
+    let la = n[2] * ((n[0] * angle / 4.0).cos() / a[0]).abs().ln();
+    let lb = n[3] * ((n[0] * angle / 4.0).sin() / a[1]).abs().ln();
+    let lm = la.max(lb);
+    let lr = if lm.is_finite() { lm + ((la - lm).exp() + (lb - lm).exp()).ln() } else { lm };
+    (-lr / n[1]).exp()
+}
+
+pub fn super_shape_3d(u:f32, v:f32, t:f32, n1:[f32; 4], n2:[f32; 4], a1:[f32; 2], a2:[f32; 2]) -> [f32; 3] {
+    let r1 = super_shape_radius(u, a1, n1);
+    let r2 = super_shape_radius(v, a2, n2);
+
+    let a = 0.334 * (2.0 + t.sin());
+    let v1 = v * a;
+    let x = r1 * u.cos() * r2 * v1.cos();
+    let y = r2 * v1.sin();
+    let z = r1 * u.sin() * r2 * v1.cos();
+    [x, y, z]
+}
+
+pub fn trefoil(t:f32) -> [f32; 3] {
+    let x = t.sin() + 2.0 * (2.0 * t).sin();
+    let y = -(3.0 * t).sin();
+    let z = t.cos() - 2.0 * (2.0 * t).cos();
+    [x, y, z]
+}
+
+// (p, q) torus knot winding p times around the axis and q times through the hole, t in [0, 2*pi]
+pub fn torus_knot(t:f32, p:f32, q:f32) -> [f32; 3] {
+    let r = 2.0 + (q * t).cos();
+    let x = r * (p * t).cos();
+    let y = -(q * t).sin();
+    let z = r * (p * t).sin();
+    [x, y, z]
+}
+
+pub fn helix(t:f32) -> [f32; 3] {
+    let x = t.cos();
+    let y = 0.1 * t;
+    let z = t.sin();
+    [x, y, z]
+}
+
+// associated Legendre function P_l^m(x) for 0 <= m <= l, including the Condon-Shortley phase.
+// it vanishes for m > l.
+pub fn associated_legendre(l:u32, m:u32, x:f32) -> f32 {
+    if m > l {
+        return 0.0;
+    }
+    let mut pmm = 1.0f32;
+    if m > 0 {
+        let somx2 = ((1.0 - x) * (1.0 + x)).sqrt();
+        let mut fact = 1.0;
+        for _ in 0..m {
+            pmm *= -fact * somx2;
+            fact += 2.0;
+        }
+    }
+    if l == m {
+        return pmm;
+    }
+    let mut pmmp1 = x * (2 * m + 1) as f32 * pmm;
+    if l == m + 1 {
+        return pmmp1;
+    }
+    let mut pll = 0.0;
+    for ll in m + 2..=l {
+        pll = (x * (2 * ll - 1) as f32 * pmmp1 - (ll + m - 1) as f32 * pmm) / (ll - m) as f32;
+        pmm = pmmp1;
+        pmmp1 = pll;
+    }
+    pll
+}
+
+// real spherical harmonic Y_lm(theta, phi) with |m| <= l, theta measured from the y-axis.
+// it is zero for |m| > l.
+pub fn spherical_harmonic(l:u32, m:i32, theta:f32, phi:f32) -> f32 {
+    let am = m.unsigned_abs();
+    if am > l {
+        return 0.0;
+    }
+    let mut ratio = 1.0f32; // (l - |m|)!/(l + |m|)!
+    for k in l - am + 1..=l + am {
+        ratio /= k as f32;
+    }
+    let k = ((2 * l + 1) as f32 / (4.0 * PI) * ratio).sqrt();
+    let p = associated_legendre(l, am, theta.cos());
+    if m > 0 {
+        2.0f32.sqrt() * k * (m as f32 * phi).cos() * p
+    } else if m < 0 {
+        2.0f32.sqrt() * k * (am as f32 * phi).sin() * p
+    } else {
+        k * p
+    }
+}
+
+// Bessel function of the first kind J_n(x) from its integral representation
+pub fn bessel_j(n:u32, x:f32) -> f32 {
+    let steps = 64;
+    let h = PI / steps as f32;
+    let mut sum = 0.0;
+    for k in 0..=steps {
+        let tau = k as f32 * h;
+        let w = if k == 0 || k == steps { 0.5 } else { 1.0 };
+        sum += w * (n as f32 * tau - x * tau.sin()).cos();
+    }
+    sum * h / PI
+}
+
+// (1, 1) vibration mode of a circular membrane with unit radius
+pub fn membrane(r:f32, theta:f32, t:f32) -> f32 {
+    bessel_j(1, 3.8317 * r) * theta.cos() * t.cos()
+}
+
+pub fn polar_ripple(r:f32, _theta:f32, t:f32) -> f32 {
+    (4.0 * PI * r - 2.0 * t).sin() / (1.0 + 4.0 * r)
+}
+
+// rational function with two zeros at +-1 and two poles at +-i, turning with t
+pub fn complex_rational(z:Complex32, t:f32) -> Complex32 {
+    let rot = Complex32::from_polar(1.0, t);
+    (z * z - rot) / (z * z + rot)
+}
+
+pub fn complex_sin(z:Complex32, t:f32) -> Complex32 {
+    (z + Complex32::new(t.cos(), 0.0)).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spherical_harmonic_out_of_range() {
+        assert_eq!(spherical_harmonic(1, 3, 0.7, 0.3), 0.0);
+        assert_eq!(spherical_harmonic(0, -1, 0.7, 0.3), 0.0);
+        assert_eq!(associated_legendre(2, 5, 0.4), 0.0);
+        // Y_00 = 1/(2 sqrt(pi))
+        assert!((spherical_harmonic(0, 0, 0.7, 0.3) - 0.5 / PI.sqrt()).abs() < 1e-6);
+    }
+
+    // samples n x n points of [umin, umax] x [vmin, vmax] including the borders
+    fn sample(f:&dyn Fn(f32, f32) -> [f32; 3], (umin, umax, vmin, vmax):(f32, f32, f32, f32)) {
+        let n = 64;
+        for i in 0..=n {
+            for j in 0..=n {
+                let u = umin + (umax - umin) * i as f32 / n as f32;
+                let v = vmin + (vmax - vmin) * j as f32 / n as f32;
+                let p = f(u, v);
+                assert!(p.iter().all(|c| c.is_finite()), "{:?} at ({}, {})", p, u, v);
+            }
+        }
+    }
+
+    // the domains set in IParametricSurface::new_into
+    #[test]
+    fn parametric_surfaces_are_finite() {
+        sample(&mobius_strip, (0.0, 2.0*PI, -0.4, 0.4));
+        sample(&dini, (0.0, 4.0*PI, 0.05, 2.0));
+        sample(&catenoid, (0.0, 2.0*PI, -1.5, 1.5));
+        sample(&helicoid, (-PI, PI, -2.0, 2.0));
+        sample(&costa, (0.05, 0.95, 0.05, 0.95));
+        sample(&cross_cap, (0.0, 2.0*PI, 0.001, 0.5*PI));
+        sample(&roman, (0.0, 2.0*PI, 0.001, 0.999*PI));
+    }
+
+    // the domains set in ISimpleSurface::preset_function, over a few time values
+    #[test]
+    fn simple_surfaces_are_finite() {
+        let presets:[(fn(f32, f32, f32) -> [f32; 3], (f32, f32, f32, f32)); 5] = [
+            (monkey_saddle, (-1.0, 1.0, -1.0, 1.0)),
+            (rosenbrock, (-2.0, 2.0, -1.0, 3.0)),
+            (himmelblau, (-5.0, 5.0, -5.0, 5.0)),
+            (ackley, (-5.0, 5.0, -5.0, 5.0)),
+            (ripple, (-10.0, 10.0, -10.0, 10.0)),
+        ];
+        for (f, domain) in presets {
+            for t in [0.0, 1.3, 10.0] {
+                sample(&|x, z| f(x, z, t), domain);
+            }
+        }
+    }
+}
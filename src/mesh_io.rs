@@ -0,0 +1,813 @@
+#![allow(dead_code)]
+use cgmath::*;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use super::surface_data::ISurfaceOutput;
+use super::colormap;
+
+// the triangles wound counter-clockwise around their vertex normals, and the sign to write the normals
+// with. the normals of the generators are opposite to their winding and the ones of vertex_data's
+// shapes follow it, while the file formats expect them to agree. a closed mesh is turned inside out
+// when its normals point inwards, as printers expect.
+fn oriented_triangles(mesh:&ISurfaceOutput) -> (Vec<[u32; 3]>, f32) {
+    let mut volume = 0.0f32;
+    let mut tris:Vec<[u32; 3]> = mesh.indices.chunks(3).map(|t| {
+        let p:Vec<Vector3<f32>> = t.iter().map(|&i| Vector3::from(mesh.positions[i as usize])).collect();
+        let n = t.iter().fold(Vector3::new(0.0, 0.0, 0.0), |n, &i| {
+            n + mesh.normals.get(i as usize).map_or(Vector3::new(0.0, 0.0, 0.0), |&n| Vector3::from(n))
+        });
+        let v = p[0].dot(p[1].cross(p[2])) / 6.0;
+        if (p[1] - p[0]).cross(p[2] - p[0]).dot(n) < 0.0 {
+            volume -= v;
+            [t[0], t[2], t[1]]
+        } else {
+            volume += v;
+            [t[0], t[1], t[2]]
+        }
+    }).collect();
+
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for t in mesh.indices.chunks(3) {
+        for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    if volume < 0.0 && edges.values().all(|&c| c == 2) {
+        for t in tris.iter_mut() {
+            t.swap(1, 2);
+        }
+        return (tris, -1.0);
+    }
+    (tris, 1.0)
+}
+
+fn face_normal(mesh:&ISurfaceOutput, t:&[u32; 3]) -> [f32; 3] {
+    let p:Vec<Vector3<f32>> = t.iter().map(|&i| Vector3::from(mesh.positions[i as usize])).collect();
+    let n = (p[1] - p[0]).cross(p[2] - p[0]);
+    if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 0.0, 0.0] }
+}
+
+// region: writers
+// Wavefront OBJ with positions, normals and uvs
+pub fn write_obj(mesh:&ISurfaceOutput, w:&mut impl Write) -> anyhow::Result<()> {
+    let (tris, sign) = oriented_triangles(mesh);
+    let mut s = String::new();
+    for p in mesh.positions.iter() {
+        writeln!(s, "v {} {} {}", p[0], p[1], p[2])?;
+    }
+    for n in mesh.normals.iter() {
+        writeln!(s, "vn {} {} {}", sign * n[0], sign * n[1], sign * n[2])?;
+    }
+    for uv in mesh.uvs.iter() {
+        writeln!(s, "vt {} {}", uv[0], uv[1])?;
+    }
+    let (has_n, has_uv) = (mesh.normals.len() == mesh.positions.len(), mesh.uvs.len() == mesh.positions.len());
+    for t in tris {
+        let [a, b, c] = t.map(|i| {
+            let i = i + 1;
+            match (has_uv, has_n) {
+                (true, true) => format!("{i}/{i}/{i}"),
+                (false, true) => format!("{i}//{i}"),
+                (true, false) => format!("{i}/{i}"),
+                _ => format!("{i}"),
+            }
+        });
+        writeln!(s, "f {a} {b} {c}")?;
+    }
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_stl_ascii(mesh:&ISurfaceOutput, w:&mut impl Write) -> anyhow::Result<()> {
+    let mut s = String::from("solid surface\n");
+    for t in oriented_triangles(mesh).0 {
+        let n = face_normal(mesh, &t);
+        writeln!(s, "  facet normal {} {} {}\n    outer loop", n[0], n[1], n[2])?;
+        for i in t {
+            let p = mesh.positions[i as usize];
+            writeln!(s, "      vertex {} {} {}", p[0], p[1], p[2])?;
+        }
+        s.push_str("    endloop\n  endfacet\n");
+    }
+    s.push_str("endsolid surface\n");
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+// binary STL: 80-byte header, triangle count, then normal, three vertices and a zero attribute word
+// per triangle, all little-endian
+pub fn write_stl_binary(mesh:&ISurfaceOutput, w:&mut impl Write) -> anyhow::Result<()> {
+    let tris = oriented_triangles(mesh).0;
+    // the header must not start with "solid", which marks an ASCII file
+    let header = b"binary STL from wgpu_gpu_surfaces";
+    let mut bytes: Vec<u8> = vec![0; 80];
+    bytes[..header.len()].copy_from_slice(header);
+    bytes.extend((tris.len() as u32).to_le_bytes());
+    for t in tris.iter() {
+        let mut values = face_normal(mesh, t).to_vec();
+        for &i in t {
+            values.extend(mesh.positions[i as usize]);
+        }
+        for v in values {
+            bytes.extend(v.to_le_bytes());
+        }
+        bytes.extend([0u8, 0]);
+    }
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+// ASCII PLY with positions, normals and 8-bit colors
+pub fn write_ply(mesh:&ISurfaceOutput, w:&mut impl Write) -> anyhow::Result<()> {
+    let (tris, sign) = oriented_triangles(mesh);
+    let has_n = mesh.normals.len() == mesh.positions.len();
+    let has_c = mesh.colors.len() == mesh.positions.len();
+    let mut s = format!("ply\nformat ascii 1.0\ncomment generated by wgpu_gpu_surfaces\nelement vertex {}\n", mesh.positions.len());
+    s.push_str("property float x\nproperty float y\nproperty float z\n");
+    if has_n {
+        s.push_str("property float nx\nproperty float ny\nproperty float nz\n");
+    }
+    if has_c {
+        s.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\n");
+    }
+    writeln!(s, "element face {}\nproperty list uchar int vertex_indices\nend_header", tris.len())?;
+    let byte = |c:f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    for (i, p) in mesh.positions.iter().enumerate() {
+        write!(s, "{} {} {}", p[0], p[1], p[2])?;
+        if has_n {
+            let n = mesh.normals[i];
+            write!(s, " {} {} {}", sign * n[0], sign * n[1], sign * n[2])?;
+        }
+        if has_c {
+            let c = mesh.colors[i];
+            write!(s, " {} {} {}", byte(c[0]), byte(c[1]), byte(c[2]))?;
+        }
+        s.push('\n');
+    }
+    for t in tris {
+        writeln!(s, "3 {} {} {}", t[0], t[1], t[2])?;
+    }
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+// binary glTF 2.0 with one mesh of two primitives: the triangles with COLOR_0 from the colormap, and
+// the wireframe as lines with COLOR_0 from the wireframe colors. both share POSITION.
+pub fn write_glb(mesh:&ISurfaceOutput, w:&mut impl Write) -> anyhow::Result<()> {
+    let n = mesh.positions.len();
+    let (tris, sign) = oriented_triangles(mesh);
+    let tris:Vec<u32> = tris.into_iter().flatten().collect();
+
+    // (data, components, accessor type, target) of every buffer view, one accessor each
+    let mut views: Vec<(Vec<u8>, usize, &str, u32)> = vec![];
+    let floats = |v:&[[f32; 3]]| v.iter().flatten().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>();
+    views.push((floats(&mesh.positions), 3, "VEC3", 34962));
+    let has_n = mesh.normals.len() == n;
+    if has_n {
+        let normals:Vec<[f32; 3]> = mesh.normals.iter().map(|n| n.map(|c| sign * c)).collect();
+        views.push((floats(&normals), 3, "VEC3", 34962));
+    }
+    // TANGENT needs NORMAL. turning the normals around flips the handedness too.
+    let has_t = has_n && mesh.tangents.len() == n;
+    if has_t {
+        views.push((mesh.tangents.iter().flat_map(|t| [t[0], t[1], t[2], sign * t[3]]).flat_map(|x| x.to_le_bytes()).collect(),
+            4, "VEC4", 34962));
+    }
+    let has_uv = mesh.uvs.len() == n;
+    if has_uv {
+        views.push((mesh.uvs.iter().flatten().flat_map(|x| x.to_le_bytes()).collect(), 2, "VEC2", 34962));
+    }
+    let colors = if mesh.colors.len() == n { mesh.colors.clone() } else { vec![[1.0, 1.0, 1.0]; n] };
+    let colors2 = if mesh.colors2.len() == n { mesh.colors2.clone() } else { vec![[1.0, 1.0, 1.0]; n] };
+    views.push((floats(&colors), 3, "VEC3", 34962));
+    views.push((floats(&colors2), 3, "VEC3", 34962));
+    views.push((tris.iter().flat_map(|i| i.to_le_bytes()).collect(), 1, "SCALAR", 34963));
+    let has_lines = !mesh.indices2.is_empty();
+    if has_lines {
+        views.push((mesh.indices2.iter().flat_map(|i| i.to_le_bytes()).collect(), 1, "SCALAR", 34963));
+    }
+
+    let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+    for p in mesh.positions.iter() {
+        for k in 0..3 {
+            pmin[k] = pmin[k].min(p[k]);
+            pmax[k] = pmax[k].max(p[k]);
+        }
+    }
+    if n == 0 {
+        (pmin, pmax) = ([0.0; 3], [0.0; 3]);
+    }
+
+    let mut bin: Vec<u8> = vec![];
+    let (mut buffer_views, mut accessors) = (vec![], vec![]);
+    for (k, (data, components, kind, target)) in views.iter().enumerate() {
+        let (component_type, count) = if *target == 34963 { (5125, data.len() / 4) } else { (5126, data.len() / 4 / components) };
+        buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, bin.len(), data.len(), target));
+        let bounds = if k == 0 {
+            format!(r#","min":[{},{},{}],"max":[{},{},{}]"#, pmin[0], pmin[1], pmin[2], pmax[0], pmax[1], pmax[2])
+        } else { String::new() };
+        accessors.push(format!(r#"{{"bufferView":{k},"componentType":{component_type},"count":{count},"type":"{kind}"{bounds}}}"#));
+        bin.extend(data);
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+    }
+
+    let mut attributes = String::from(r#""POSITION":0"#);
+    let mut k = 1;
+    if has_n {
+        write!(attributes, r#","NORMAL":{k}"#)?;
+        k += 1;
+    }
+    if has_t {
+        write!(attributes, r#","TANGENT":{k}"#)?;
+        k += 1;
+    }
+    if has_uv {
+        write!(attributes, r#","TEXCOORD_0":{k}"#)?;
+        k += 1;
+    }
+    let (color, color2, tri_indices) = (k, k + 1, k + 2);
+    let mut primitives = format!(r#"{{"attributes":{{{attributes},"COLOR_0":{color}}},"indices":{tri_indices},"mode":4}}"#);
+    if has_lines {
+        write!(primitives, r#",{{"attributes":{{"POSITION":0,"COLOR_0":{color2}}},"indices":{},"mode":1}}"#, tri_indices + 1)?;
+    }
+    let json = format!(concat!(r#"{{"asset":{{"version":"2.0","generator":"wgpu_gpu_surfaces"}},"scene":0,"#,
+        r#""scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{}]}}],"#,
+        r#""buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]}}"#),
+        primitives, bin.len(), buffer_views.join(","), accessors.join(","));
+    let mut json = json.into_bytes();
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+
+    let mut bytes: Vec<u8> = vec![];
+    bytes.extend(b"glTF");
+    bytes.extend(2u32.to_le_bytes());
+    bytes.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+    bytes.extend((json.len() as u32).to_le_bytes());
+    bytes.extend(b"JSON");
+    bytes.extend(json);
+    bytes.extend((bin.len() as u32).to_le_bytes());
+    bytes.extend(b"BIN\0");
+    bytes.extend(bin);
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+// write the mesh in the format given by the extension of path: obj, stl (binary), ply or glb
+pub fn save_mesh<P: AsRef<Path>>(mesh:&ISurfaceOutput, path:P) -> anyhow::Result<()> {
+    let ext = path.as_ref().extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path.as_ref())?);
+    match ext.as_str() {
+        "obj" => write_obj(mesh, &mut file)?,
+        "stl" => write_stl_binary(mesh, &mut file)?,
+        "ply" => write_ply(mesh, &mut file)?,
+        "glb" => write_glb(mesh, &mut file)?,
+        _ => anyhow::bail!("unknown mesh format: {:?}", path.as_ref()),
+    }
+    file.flush()?;
+    Ok(())
+}
+// endregion: writers
+
+// region: readers
+pub struct IMeshImport {
+    pub scale: f32, // fit the mesh into [-scale, scale], or keep its coordinates with 0
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction, 3: vertex colors of the file
+}
+
+impl Default for IMeshImport {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+        }
+    }
+}
+
+impl IMeshImport {
+    // load an OBJ or PLY file by its extension. normals missing from the file are computed from the
+    // faces, and the wireframe is made of the unique triangle edges.
+    pub fn load<P: AsRef<Path>>(&self, path:P) -> anyhow::Result<ISurfaceOutput> {
+        let ext = path.as_ref().extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let bytes = std::fs::read(path.as_ref())?;
+        let mut mesh = match ext.as_str() {
+            "obj" => read_obj(&String::from_utf8_lossy(&bytes))?,
+            "ply" => read_ply(&bytes)?,
+            _ => anyhow::bail!("unknown mesh format: {:?}", path.as_ref()),
+        };
+        if mesh.normals.len() != mesh.positions.len() {
+            mesh.compute_normals();
+        }
+        if mesh.uvs.len() != mesh.positions.len() {
+            mesh.uvs = vec![[0.0, 0.0]; mesh.positions.len()];
+        }
+        mesh.compute_tangents();
+        mesh.indices2 = mesh.edge_indices();
+        if self.scale > 0.0 {
+            mesh.normalize_positions(self.scale);
+        }
+
+        if self.colormap_direction == 3 && mesh.colors.len() == mesh.positions.len() {
+            let cdata2 = colormap::colormap_data(&self.wireframe_color);
+            mesh.colors2 = vec![colormap::color_lerp(cdata2, 0.0, 1.0, 1.0); mesh.positions.len()];
+        } else {
+            let direction = if self.colormap_direction > 2 { 1 } else { self.colormap_direction };
+            mesh.set_colormap(&self.colormap_name, &self.wireframe_color, direction);
+        }
+        Ok(mesh)
+    }
+}
+
+// 1-based OBJ index, negative when counted back from the end of the list read so far
+fn obj_index(token:&str, len:usize) -> anyhow::Result<Option<usize>> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let i:i64 = token.parse()?;
+    let idx = if i < 0 { len as i64 + i } else { i - 1 };
+    if idx < 0 || idx >= len as i64 {
+        anyhow::bail!("OBJ index {} out of range", i);
+    }
+    Ok(Some(idx as usize))
+}
+
+// Wavefront OBJ with v, vt, vn and polygonal f records. every distinct v/vt/vn triple becomes a
+// vertex, and polygons are split into fans. normals are left out unless every corner has one.
+pub fn read_obj(text:&str) -> anyhow::Result<ISurfaceOutput> {
+    let mut vs: Vec<[f32; 3]> = vec![];
+    let mut vts: Vec<[f32; 2]> = vec![];
+    let mut vns: Vec<[f32; 3]> = vec![];
+    let mut corners: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut keys: Vec<(usize, Option<usize>, Option<usize>)> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let values = |tokens:std::str::SplitWhitespace| -> anyhow::Result<Vec<f32>> {
+            Ok(tokens.map(|t| t.parse::<f32>()).collect::<Result<Vec<f32>, _>>()?)
+        };
+        match tokens.next() {
+            Some("v") => {
+                let v = values(tokens)?;
+                anyhow::ensure!(v.len() >= 3, "OBJ vertex needs 3 coordinates: {}", line);
+                vs.push([v[0], v[1], v[2]]);
+            }
+            Some("vt") => {
+                let v = values(tokens)?;
+                anyhow::ensure!(!v.is_empty(), "OBJ texture coordinate is empty: {}", line);
+                vts.push([v[0], v.get(1).copied().unwrap_or(0.0)]);
+            }
+            Some("vn") => {
+                let v = values(tokens)?;
+                anyhow::ensure!(v.len() >= 3, "OBJ normal needs 3 components: {}", line);
+                vns.push([v[0], v[1], v[2]]);
+            }
+            Some("f") => {
+                let mut face: Vec<u32> = vec![];
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let v = obj_index(parts.next().unwrap_or_default(), vs.len())?
+                        .ok_or_else(|| anyhow::anyhow!("OBJ face corner without a vertex: {}", line))?;
+                    let vt = obj_index(parts.next().unwrap_or_default(), vts.len())?;
+                    let vn = obj_index(parts.next().unwrap_or_default(), vns.len())?;
+                    let idx = *corners.entry((v, vt, vn)).or_insert_with(|| {
+                        keys.push((v, vt, vn));
+                        keys.len() as u32 - 1
+                    });
+                    face.push(idx);
+                }
+                anyhow::ensure!(face.len() >= 3, "OBJ face needs 3 corners: {}", line);
+                for k in 1..face.len() - 1 {
+                    indices.extend([face[0], face[k], face[k + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut mesh = ISurfaceOutput { indices, ..Default::default() };
+    mesh.positions = keys.iter().map(|k| vs[k.0]).collect();
+    if keys.iter().all(|k| k.1.is_some()) {
+        mesh.uvs = keys.iter().map(|k| vts[k.1.unwrap()]).collect();
+    }
+    if keys.iter().all(|k| k.2.is_some()) {
+        mesh.normals = keys.iter().map(|k| vns[k.2.unwrap()]).collect();
+    }
+    Ok(mesh)
+}
+
+// PLY property: (name, scalar type, list count type)
+type IPlyProperty = (String, String, Option<String>);
+
+fn ply_size(kind:&str) -> anyhow::Result<usize> {
+    Ok(match kind {
+        "char" | "uchar" | "int8" | "uint8" => 1,
+        "short" | "ushort" | "int16" | "uint16" => 2,
+        "int" | "uint" | "float" | "int32" | "uint32" | "float32" => 4,
+        "double" | "float64" => 8,
+        _ => anyhow::bail!("unknown PLY type {}", kind),
+    })
+}
+
+// one scalar of a binary PLY body, advancing pos
+fn ply_binary_value(bytes:&[u8], pos:&mut usize, kind:&str, big_endian:bool) -> anyhow::Result<f64> {
+    let n = ply_size(kind)?;
+    anyhow::ensure!(*pos + n <= bytes.len(), "PLY body is truncated");
+    let mut b = bytes[*pos..*pos + n].to_vec();
+    *pos += n;
+    if big_endian {
+        b.reverse();
+    }
+    Ok(match kind {
+        "char" | "int8" => b[0] as i8 as f64,
+        "uchar" | "uint8" => b[0] as f64,
+        "short" | "int16" => i16::from_le_bytes([b[0], b[1]]) as f64,
+        "ushort" | "uint16" => u16::from_le_bytes([b[0], b[1]]) as f64,
+        "int" | "int32" => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        "uint" | "uint32" => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        "float" | "float32" => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        _ => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+    })
+}
+
+// ASCII or binary PLY with a vertex element (x, y, z and optional nx, ny, nz, red, green, blue and
+// s, t or u, v) and a face element with vertex_indices. polygons are split into fans and other
+// elements are skipped.
+pub fn read_ply(bytes:&[u8]) -> anyhow::Result<ISurfaceOutput> {
+    let end = bytes.windows(10).position(|w| w == b"end_header")
+        .ok_or_else(|| anyhow::anyhow!("PLY header has no end_header"))?;
+    let header = String::from_utf8_lossy(&bytes[..end]);
+    let mut pos = end + 10;
+    while pos < bytes.len() && bytes[pos] != b'\n' {
+        pos += 1;
+    }
+    pos += 1;
+
+    let mut format = String::new();
+    let mut elements: Vec<(String, usize, Vec<IPlyProperty>)> = vec![];
+    for (k, line) in header.lines().enumerate() {
+        let t:Vec<&str> = line.split_whitespace().collect();
+        match t.first().copied() {
+            Some("ply") if k == 0 => {}
+            Some("format") if t.len() >= 2 => format = t[1].to_string(),
+            Some("element") if t.len() >= 3 => elements.push((t[1].to_string(), t[2].parse()?, vec![])),
+            Some("property") if t.len() >= 5 && t[1] == "list" => {
+                let e = elements.last_mut().ok_or_else(|| anyhow::anyhow!("PLY property before any element"))?;
+                e.2.push((t[4].to_string(), t[3].to_string(), Some(t[2].to_string())));
+            }
+            Some("property") if t.len() >= 3 => {
+                let e = elements.last_mut().ok_or_else(|| anyhow::anyhow!("PLY property before any element"))?;
+                e.2.push((t[2].to_string(), t[1].to_string(), None));
+            }
+            _ if k == 0 => anyhow::bail!("not a PLY file"),
+            _ => {}
+        }
+    }
+    let binary = match format.as_str() {
+        "ascii" => None,
+        "binary_little_endian" => Some(false),
+        "binary_big_endian" => Some(true),
+        _ => anyhow::bail!("unknown PLY format {:?}", format),
+    };
+
+    // every record as its flat list of values, with each list property as its count and items
+    let text = if binary.is_none() { String::from_utf8_lossy(&bytes[pos.min(bytes.len())..]) } else { "".into() };
+    let mut words = text.split_whitespace();
+    let mut records: HashMap<String, Vec<Vec<f64>>> = HashMap::new();
+    for (name, count, props) in elements.iter() {
+        // the count comes from the header, and every record takes at least one byte of the body
+        let mut list: Vec<Vec<f64>> = Vec::with_capacity((*count).min(bytes.len().saturating_sub(pos)));
+        for _ in 0..*count {
+            let mut record: Vec<f64> = vec![];
+            for (_, kind, count_kind) in props.iter() {
+                let mut value = |kind:&str| -> anyhow::Result<f64> {
+                    match binary {
+                        Some(big_endian) => ply_binary_value(bytes, &mut pos, kind, big_endian),
+                        None => Ok(words.next().ok_or_else(|| anyhow::anyhow!("PLY body is truncated"))?.parse()?),
+                    }
+                };
+                match count_kind {
+                    Some(count_kind) => {
+                        let n = value(count_kind)?;
+                        record.push(n);
+                        for _ in 0..n as usize {
+                            record.push(value(kind)?);
+                        }
+                    }
+                    None => record.push(value(kind)?),
+                }
+            }
+            list.push(record);
+        }
+        records.insert(name.clone(), list);
+    }
+
+    let (_, _, vprops) = elements.iter().find(|e| e.0 == "vertex")
+        .ok_or_else(|| anyhow::anyhow!("PLY file has no vertex element"))?;
+    anyhow::ensure!(vprops.iter().all(|p| p.2.is_none()), "PLY vertex lists are not supported");
+    let column = |names:&[&str]| names.iter().map(|n| vprops.iter().position(|p| p.0 == *n)).collect::<Option<Vec<usize>>>();
+    let vertices = &records["vertex"];
+    let mut mesh = ISurfaceOutput::default();
+    let xyz = column(&["x", "y", "z"]).ok_or_else(|| anyhow::anyhow!("PLY vertices need x, y and z"))?;
+    mesh.positions = vertices.iter().map(|r| [r[xyz[0]] as f32, r[xyz[1]] as f32, r[xyz[2]] as f32]).collect();
+    if let Some(n) = column(&["nx", "ny", "nz"]) {
+        mesh.normals = vertices.iter().map(|r| [r[n[0]] as f32, r[n[1]] as f32, r[n[2]] as f32]).collect();
+    }
+    if let Some(c) = column(&["red", "green", "blue"]) {
+        // integer colors are 8-bit, float colors are in [0, 1]
+        let k = if vprops[c[0]].1.contains("char") || vprops[c[0]].1.contains("int") { 1.0 / 255.0 } else { 1.0 };
+        mesh.colors = vertices.iter().map(|r| [(r[c[0]] * k) as f32, (r[c[1]] * k) as f32, (r[c[2]] * k) as f32]).collect();
+    }
+    if let Some(uv) = column(&["s", "t"]).or_else(|| column(&["u", "v"])).or_else(|| column(&["texture_u", "texture_v"])) {
+        mesh.uvs = vertices.iter().map(|r| [r[uv[0]] as f32, r[uv[1]] as f32]).collect();
+    }
+
+    if let Some((_, _, fprops)) = elements.iter().find(|e| e.0 == "face") {
+        let list = fprops.iter().position(|p| p.0 == "vertex_indices" || p.0 == "vertex_index")
+            .ok_or_else(|| anyhow::anyhow!("PLY faces need vertex_indices"))?;
+        for r in records["face"].iter() {
+            // skip the values of the properties before the index list
+            let mut k = 0;
+            for p in fprops[..list].iter() {
+                k += if p.2.is_some() { 1 + r[k] as usize } else { 1 };
+            }
+            let face:Vec<u32> = r[k + 1..k + 1 + r[k] as usize].iter().map(|&i| i as u32).collect();
+            anyhow::ensure!(face.len() >= 3 && face.iter().all(|&i| (i as usize) < mesh.positions.len()),
+                "PLY face is invalid: {:?}", face);
+            for j in 1..face.len() - 1 {
+                mesh.indices.extend([face[0], face[j], face[j + 1]]);
+            }
+        }
+    }
+    Ok(mesh)
+}
+// endregion: readers
+
+#[cfg(test)]
+mod tests {
+    use super::super::surface_data::IParametricSurface;
+    use super::*;
+
+    fn torus(weld_seams:bool) -> ISurfaceOutput {
+        IParametricSurface { surface_type: 21, u_resolution: 8, v_resolution: 6, weld_seams, ..Default::default() }.new()
+    }
+
+    fn write(writer:fn(&ISurfaceOutput, &mut Vec<u8>) -> anyhow::Result<()>, mesh:&ISurfaceOutput) -> Vec<u8> {
+        let mut bytes = vec![];
+        writer(mesh, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn u32_at(bytes:&[u8], pos:usize) -> usize {
+        u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]) as usize
+    }
+
+    // every number following "key": in the JSON text, in order
+    fn json_numbers(json:&str, key:&str) -> Vec<usize> {
+        let pattern = format!("\"{}\":", key);
+        json.match_indices(&pattern).map(|(i, _)| {
+            let rest = &json[i + pattern.len()..];
+            rest[..rest.find(|c:char| !c.is_ascii_digit()).unwrap()].parse().unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn closed_inward_meshes_are_turned_outwards() {
+        let mut mesh = torus(true);
+        let volume = |tris:&[[u32; 3]]| tris.iter().map(|t| {
+            let p = t.map(|i| Vector3::from(mesh.positions[i as usize]));
+            p[0].dot(p[1].cross(p[2])) / 6.0
+        }).sum::<f32>();
+        let (tris, sign) = oriented_triangles(&mesh);
+        mesh.normals.iter_mut().for_each(|n| *n = n.map(|c| -c));
+        let (flipped, flipped_sign) = oriented_triangles(&mesh);
+        assert_eq!(sign, -flipped_sign);
+        assert_eq!(tris, flipped);
+        assert!(volume(&tris) > 0.0);
+        // the normals written with the sign still follow the winding
+        for t in tris.iter() {
+            let p = t.map(|i| Vector3::from(mesh.positions[i as usize]));
+            let n = t.iter().fold(Vector3::new(0.0, 0.0, 0.0), |n, &i| n + Vector3::from(mesh.normals[i as usize]));
+            assert!((p[1] - p[0]).cross(p[2] - p[0]).dot(n * flipped_sign) > 0.0);
+        }
+        // an open mesh keeps the side of its normals
+        let mut open = torus(false);
+        open.indices.truncate(open.indices.len() - 3);
+        assert_eq!(oriented_triangles(&open).1, 1.0);
+    }
+
+    #[test]
+    fn binary_stl_layout() {
+        let mesh = torus(false);
+        let bytes = write(write_stl_binary, &mesh);
+        let tris = oriented_triangles(&mesh).0;
+        assert!(!bytes.starts_with(b"solid"));
+        assert_eq!(u32_at(&bytes, 80), tris.len());
+        assert_eq!(bytes.len(), 84 + 50 * tris.len());
+        for (k, t) in tris.iter().enumerate() {
+            let record = &bytes[84 + 50 * k..84 + 50 * (k + 1)];
+            let value = |i:usize| f32::from_le_bytes([record[4 * i], record[4 * i + 1], record[4 * i + 2], record[4 * i + 3]]);
+            for (c, &i) in t.iter().enumerate() {
+                assert_eq!([value(3 + 3 * c), value(4 + 3 * c), value(5 + 3 * c)], mesh.positions[i as usize]);
+            }
+            assert_eq!(&record[48..], &[0, 0]);
+        }
+    }
+
+    #[test]
+    fn obj_round_trip() {
+        let mesh = torus(false);
+        let bytes = write(write_obj, &mesh);
+        let text = String::from_utf8(bytes).unwrap();
+        // faces are 1-based with the same index for v, vt and vn
+        let faces:Vec<&str> = text.lines().filter(|l| l.starts_with("f ")).collect();
+        let (tris, sign) = oriented_triangles(&mesh);
+        assert_eq!(faces.len(), tris.len());
+        let t = tris[0].map(|i| i + 1);
+        assert_eq!(faces[0], format!("f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", t[0], t[1], t[2]));
+        assert!(!text.contains(" 0/"));
+
+        let read = read_obj(&text).unwrap();
+        let corners:Vec<u32> = tris.iter().flatten().copied().collect();
+        assert_eq!(read.indices.len(), corners.len());
+        for (&r, &i) in read.indices.iter().zip(corners.iter()) {
+            let (r, i) = (r as usize, i as usize);
+            assert_eq!(read.positions[r], mesh.positions[i]);
+            assert_eq!(read.normals[r], mesh.normals[i].map(|c| sign * c));
+            assert_eq!(read.uvs[r], mesh.uvs[i]);
+        }
+    }
+
+    #[test]
+    fn ply_round_trip() {
+        let mesh = torus(false);
+        let read = read_ply(&write(write_ply, &mesh)).unwrap();
+        let (tris, sign) = oriented_triangles(&mesh);
+        assert_eq!(read.positions, mesh.positions);
+        assert_eq!(read.indices, tris.iter().flatten().copied().collect::<Vec<u32>>());
+        for (r, n) in read.normals.iter().zip(mesh.normals.iter()) {
+            assert_eq!(*r, n.map(|c| sign * c));
+        }
+        for (r, c) in read.colors.iter().zip(mesh.colors.iter()) {
+            assert!((0..3).all(|k| (r[k] - c[k]).abs() <= 0.5 / 255.0 + 1e-6));
+        }
+    }
+
+    #[test]
+    fn glb_chunks_agree_with_the_json() {
+        let mesh = torus(false);
+        let bytes = write(write_glb, &mesh);
+        assert_eq!(&bytes[..4], b"glTF");
+        assert_eq!(u32_at(&bytes, 4), 2);
+        assert_eq!(u32_at(&bytes, 8), bytes.len());
+
+        let json_len = u32_at(&bytes, 12);
+        assert_eq!(&bytes[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = std::str::from_utf8(&bytes[20..20 + json_len]).unwrap();
+        let bin_start = 20 + json_len;
+        let bin_len = u32_at(&bytes, bin_start);
+        assert_eq!(&bytes[bin_start + 4..bin_start + 8], b"BIN\0");
+        assert_eq!(bin_len % 4, 0);
+        assert_eq!(bin_start + 8 + bin_len, bytes.len());
+
+        // the buffer comes first, then one view per accessor
+        let lengths = json_numbers(json, "byteLength");
+        let offsets = json_numbers(json, "byteOffset");
+        assert_eq!(lengths[0], bin_len);
+        let views = &lengths[1..];
+        assert_eq!(views.len(), offsets.len());
+        for (k, (&offset, &len)) in offsets.iter().zip(views.iter()).enumerate() {
+            assert_eq!(offset % 4, 0);
+            assert!(offset + len <= bin_len);
+            if k + 1 < offsets.len() {
+                assert_eq!(offsets[k + 1], offset + len.div_ceil(4) * 4);
+            }
+        }
+        let components = |kind:&str| match kind { "SCALAR" => 1, "VEC2" => 2, "VEC3" => 3, _ => 4 };
+        let kinds:Vec<&str> = json.match_indices("\"type\":\"").map(|(i, _)| {
+            let rest = &json[i + 8..];
+            &rest[..rest.find('"').unwrap()]
+        }).collect();
+        let counts = json_numbers(json, "count");
+        assert_eq!(json_numbers(json, "bufferView"), (0..views.len()).collect::<Vec<usize>>());
+        for k in 0..views.len() {
+            assert_eq!(counts[k] * components(kinds[k]) * 4, views[k], "accessor {}", k);
+        }
+        assert_eq!(counts[0], mesh.positions.len());
+    }
+
+    #[test]
+    fn ply_counts_beyond_the_body_fail() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 4000000000000\nproperty float x\nproperty float y\n\
+            property float z\nend_header\n0 0 0\n1 0 0\n";
+        assert!(read_ply(header.as_bytes()).is_err());
+        let binary = "ply\nformat binary_little_endian 1.0\nelement vertex 4000000000000\nproperty float x\nend_header\n";
+        let mut bytes = binary.as_bytes().to_vec();
+        bytes.extend(1.0f32.to_le_bytes());
+        assert!(read_ply(&bytes).is_err());
+    }
+
+    #[test]
+    fn obj_faces() {
+        // a quad fanned into two triangles, then a triangle counted back from the end of the lists
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1 4//1\nf -4//-1 -3//-1 -1//-1\n";
+        let mesh = read_obj(text).unwrap();
+        assert_eq!(mesh.positions, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3, 0, 1, 3]);
+        assert_eq!(mesh.normals, vec![[0.0, 0.0, 1.0]; 4]);
+        assert!(mesh.uvs.is_empty());
+
+        // every distinct v/vt/vn triple is a vertex, and a corner without a normal drops them all
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3\nf 1/2/1 3/3/1 2/1/1\n";
+        let mesh = read_obj(text).unwrap();
+        assert_eq!(mesh.positions.len(), 6);
+        assert_eq!(mesh.uvs[3], [1.0, 0.0]);
+        assert_eq!(mesh.positions[3], [0.0, 0.0, 0.0]);
+        assert!(mesh.normals.is_empty());
+    }
+
+    #[test]
+    fn malformed_obj_faces_fail() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        for face in ["f 1 2", "f 1 2 4", "f 0 1 2", "f -4 1 2", "f /1 2 3", "f a b c", "f 1/1 2/1 3/1"] {
+            assert!(read_obj(&format!("{}{}\n", vertices, face)).is_err(), "{}", face);
+        }
+        assert!(read_obj("v 0 0\n").is_err());
+    }
+
+    #[test]
+    fn binary_big_endian_ply() {
+        // the face has a scalar and a list before its vertex_indices
+        let header = "ply\nformat binary_big_endian 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+            property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\n\
+            property uchar flags\nproperty list uchar int material\nproperty list uchar int vertex_indices\nend_header\n";
+        let mut bytes = header.as_bytes().to_vec();
+        let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 2.5]];
+        for (k, p) in positions.iter().enumerate() {
+            for c in p {
+                bytes.extend(c.to_be_bytes());
+            }
+            bytes.extend([255u8, 0, 51 * k as u8]);
+        }
+        bytes.push(7);
+        bytes.push(2);
+        bytes.extend(5i32.to_be_bytes());
+        bytes.extend(6i32.to_be_bytes());
+        bytes.push(4);
+        for i in [0i32, 1, 2, 3] {
+            bytes.extend(i.to_be_bytes());
+        }
+        let mesh = read_ply(&bytes).unwrap();
+        assert_eq!(mesh.positions, positions.to_vec());
+        assert_eq!(mesh.colors[3], [1.0, 0.0, 153.0 / 255.0]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert!(read_ply(&bytes[..bytes.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn ascii_ply_colors() {
+        let body = |kind:&str, color:&str| format!("ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+            property float y\nproperty float z\nproperty {0} red\nproperty {0} green\nproperty {0} blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 {1}\n1 0 0 {1}\n0 1 0 {1}\n3 0 1 2\n", kind, color);
+        let floats = read_ply(body("float", "0.5 0.25 1").as_bytes()).unwrap();
+        assert_eq!(floats.colors, vec![[0.5, 0.25, 1.0]; 3]);
+        let bytes = read_ply(body("uchar", "255 0 51").as_bytes()).unwrap();
+        assert_eq!(bytes.colors, vec![[1.0, 0.0, 0.2]; 3]);
+        assert_eq!(bytes.indices, vec![0, 1, 2]);
+        // an index past the vertices
+        assert!(read_ply(body("uchar", "255 0 51").replace("3 0 1 2", "3 0 1 3").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn import_fills_what_the_file_lacks() {
+        let path = |ext:&str| std::env::temp_dir().join(format!("wgpu_gpu_surfaces_{}_import.{}", std::process::id(), ext));
+        let obj = path("obj");
+        std::fs::write(&obj, "v 0 0 0\nv 2 0 0\nv 2 2 0\nv 0 2 0\nf 1 2 3 4\n").unwrap();
+        let mesh = IMeshImport { scale: 0.0, ..Default::default() }.load(&obj).unwrap();
+        std::fs::remove_file(&obj).unwrap();
+        // normals from the counter-clockwise faces, the wireframe on the 5 unique edges
+        assert_eq!(mesh.normals, vec![[0.0, 0.0, 1.0]; 4]);
+        assert_eq!(mesh.indices2.len(), 10);
+        assert_eq!(mesh.positions[2], [2.0, 2.0, 0.0]);
+        assert_eq!(mesh.uvs.len(), 4);
+        assert_eq!(mesh.tangents.len(), 4);
+
+        let ply = path("ply");
+        std::fs::write(&ply, "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+            property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\n\
+            property list uchar int vertex_indices\nend_header\n0 0 0 255 0 0\n4 0 0 0 255 0\n0 4 0 0 0 255\n3 0 1 2\n").unwrap();
+        let file_colors = IMeshImport { colormap_direction: 3, ..Default::default() }.load(&ply).unwrap();
+        let colormap = IMeshImport::default().load(&ply).unwrap();
+        std::fs::remove_file(&ply).unwrap();
+        assert_eq!(file_colors.colors, vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert!(file_colors.colors2.iter().all(|c| *c == file_colors.colors2[0]));
+        assert_ne!(colormap.colors, file_colors.colors);
+        // fitted into [-1, 1]
+        assert!(colormap.positions.iter().flatten().all(|c| c.abs() <= 1.0 + 1e-6));
+    }
+}
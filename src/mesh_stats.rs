@@ -0,0 +1,240 @@
+#![allow(dead_code)]
+use cgmath::*;
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use super::surface_data::ISurfaceOutput;
+
+#[derive(Debug, Clone, Default)]
+pub struct IMeshStats {
+    pub vertices: usize,
+    pub edges: usize,
+    pub faces: usize,
+    pub bbox_min: [f32; 3],
+    pub bbox_max: [f32; 3],
+    pub area: f32,
+    pub volume: f32, // enclosed volume of a closed orientable mesh, 0 otherwise
+    pub components: usize,
+    pub boundary_loops: usize,
+    pub non_manifold_edges: usize, // edges shared by more than two triangles
+    pub euler_characteristic: i32,
+    pub orientable: bool,
+    pub genus: i32, // number of handles if orientable, number of cross-caps otherwise, -1 if not a manifold
+}
+
+// union-find over the vertex indices
+fn find(parent:&mut [usize], i:usize) -> usize {
+    let mut r = i;
+    while parent[r] != r {
+        r = parent[r];
+    }
+    let mut k = i;
+    while parent[k] != r {
+        (parent[k], k) = (r, parent[k]);
+    }
+    r
+}
+
+fn union(parent:&mut [usize], a:usize, b:usize) {
+    let (ra, rb) = (find(parent, a), find(parent, b));
+    if ra != rb {
+        parent[ra] = rb;
+    }
+}
+
+impl ISurfaceOutput {
+    // a copy with its seams sewn and its poles closed. coincident vertices are merged only along edges
+    // of zero length and across boundary edges that lie on top of each other, so that the sheets that
+    // pass through each other at a self-intersection (the triple point of boy_shape, the double curve
+    // of figure8) stay apart.
+    fn sewn(&self) -> ISurfaceOutput {
+        let class = self.clone().weld_vertices();
+        let mut uses: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in self.indices.chunks(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *uses.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let mut parent:Vec<usize> = (0..self.positions.len()).collect();
+        let mut seams: HashMap<(u32, u32), (usize, usize)> = HashMap::new();
+        for (&(a, b), &count) in uses.iter() {
+            let (a, b) = (a as usize, b as usize);
+            let (ca, cb) = (class[a], class[b]);
+            if ca == cb {
+                union(&mut parent, a, b);
+            } else if count == 1 {
+                // the endpoints are ordered by position so that the twin edge matches them one by one
+                let (a, b) = if ca < cb { (a, b) } else { (b, a) };
+                match seams.entry((ca.min(cb), ca.max(cb))) {
+                    Entry::Occupied(e) => {
+                        let (c, d) = *e.get();
+                        union(&mut parent, a, c);
+                        union(&mut parent, b, d);
+                    }
+                    Entry::Vacant(e) => {
+                        e.insert((a, b));
+                    }
+                }
+            }
+        }
+
+        let mut mesh = ISurfaceOutput { positions: self.positions.clone(), ..Default::default() };
+        for t in self.indices.chunks(3) {
+            let t = [t[0], t[1], t[2]].map(|i| find(&mut parent, i as usize) as u32);
+            if t[0] != t[1] && t[1] != t[2] && t[2] != t[0] {
+                mesh.indices.extend(t);
+            }
+        }
+        mesh.remove_unused_vertices();
+        mesh
+    }
+
+    // topology and geometry of the mesh after sewing its seams and closing its poles. the Euler
+    // characteristic chi = V - E + F gives the genus from chi = 2c - 2g - b (orientable) or
+    // chi = 2c - g - b (non-orientable) with c components and b boundary loops. a surface that folds
+    // onto itself or runs into itself along a curve through its samples, such as steiner or cross_cap,
+    // has edges shared by more than two triangles there and is reported with genus -1.
+    pub fn mesh_stats(&self) -> IMeshStats {
+        let mesh = self.sewn();
+        let pts:Vec<Vector3<f32>> = mesh.positions.iter().map(|p| Vector3::from(*p)).collect();
+        let tris:Vec<[usize; 3]> = mesh.indices.chunks(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect();
+
+        let mut stats = IMeshStats { vertices: pts.len(), faces: tris.len(), ..Default::default() };
+        let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in mesh.positions.iter() {
+            for k in 0..3 {
+                pmin[k] = pmin[k].min(p[k]);
+                pmax[k] = pmax[k].max(p[k]);
+            }
+        }
+        if !pts.is_empty() {
+            (stats.bbox_min, stats.bbox_max) = (pmin, pmax);
+        }
+
+        // directed use of every edge: (triangle, true when the triangle runs from the smaller index)
+        let mut edges: HashMap<(usize, usize), Vec<(usize, bool)>> = HashMap::new();
+        for (f, t) in tris.iter().enumerate() {
+            stats.area += 0.5 * (pts[t[1]] - pts[t[0]]).cross(pts[t[2]] - pts[t[0]]).magnitude();
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                edges.entry((a.min(b), a.max(b))).or_default().push((f, a < b));
+            }
+        }
+        stats.edges = edges.len();
+        stats.non_manifold_edges = edges.values().filter(|e| e.len() > 2).count();
+        stats.euler_characteristic = stats.vertices as i32 - stats.edges as i32 + stats.faces as i32;
+
+        let mut parent:Vec<usize> = (0..pts.len()).collect();
+        for t in tris.iter() {
+            union(&mut parent, t[0], t[1]);
+            union(&mut parent, t[1], t[2]);
+        }
+        stats.components = (0..pts.len()).filter(|&i| find(&mut parent, i) == i).count();
+
+        // boundary loops are the connected pieces of the edges used by a single triangle
+        let mut parent:Vec<usize> = (0..pts.len()).collect();
+        let mut on_boundary = vec![false; pts.len()];
+        for (&(a, b), e) in edges.iter() {
+            if e.len() == 1 {
+                union(&mut parent, a, b);
+                (on_boundary[a], on_boundary[b]) = (true, true);
+            }
+        }
+        stats.boundary_loops = (0..pts.len()).filter(|&i| on_boundary[i] && find(&mut parent, i) == i).count();
+
+        // flip the triangles so that neighbours run through their shared edge in opposite directions.
+        // a triangle that would have to be flipped both ways makes the mesh non-orientable.
+        let mut flip: Vec<Option<bool>> = vec![None; tris.len()];
+        let mut adjacent: Vec<Vec<(usize, bool)>> = vec![vec![]; tris.len()];
+        for e in edges.values().filter(|e| e.len() == 2) {
+            let same = e[0].1 == e[1].1;
+            adjacent[e[0].0].push((e[1].0, same));
+            adjacent[e[1].0].push((e[0].0, same));
+        }
+        // each piece also adds up its signed volume by the divergence theorem
+        let signed_volume = |t:&[usize; 3]| pts[t[0]].dot(pts[t[1]].cross(pts[t[2]])) / 6.0;
+        let mut volume = 0.0f32;
+        stats.orientable = true;
+        for start in 0..tris.len() {
+            if flip[start].is_some() {
+                continue;
+            }
+            flip[start] = Some(false);
+            let mut piece_volume = 0.0f32;
+            let mut queue = VecDeque::from([start]);
+            while let Some(f) = queue.pop_front() {
+                let ff = flip[f].unwrap();
+                piece_volume += if ff { -signed_volume(&tris[f]) } else { signed_volume(&tris[f]) };
+                for &(g, same) in adjacent[f].iter() {
+                    match flip[g] {
+                        None => {
+                            flip[g] = Some(ff ^ same);
+                            queue.push_back(g);
+                        }
+                        Some(fg) => if fg != ff ^ same {
+                            stats.orientable = false;
+                        }
+                    }
+                }
+            }
+            volume += piece_volume.abs();
+        }
+
+        let chi = 2 * stats.components as i32 - stats.boundary_loops as i32 - stats.euler_characteristic;
+        stats.genus = if stats.non_manifold_edges > 0 { -1 } else if stats.orientable { chi / 2 } else { chi };
+        if stats.orientable && stats.boundary_loops == 0 && stats.non_manifold_edges == 0 {
+            stats.volume = volume;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::surface_data::IParametricSurface;
+    use super::*;
+
+    fn stats(key:u32, weld_seams:bool) -> IMeshStats {
+        let mut surface = IParametricSurface { surface_type: key, u_resolution: 64, v_resolution: 64, weld_seams,
+            ..Default::default() };
+        surface.new().mesh_stats()
+    }
+
+    // (surface type, chi, orientable, boundary loops) of the closed and bounded surfaces of the catalog
+    #[test]
+    fn parametric_catalog() {
+        let expected = [
+            (21, 0, true, 0), // torus
+            (16, 2, true, 0), // pear
+            (1, 2, true, 0), (2, 2, true, 0), // astroid, astroid2
+            (0, 0, false, 0), (11, 0, false, 0), (8, 0, false, 0), // klein_bottle, klein_bottle2, figure8
+            (12, 0, true, 0), // klein_bottle3: its cross-section turns 3 whole times, so it closes up as a torus
+            (5, 1, false, 0), // boy_shape, the projective plane
+            (23, 0, false, 1), // mobius_strip
+            (25, 0, true, 2), // catenoid
+        ];
+        for weld_seams in [false, true] {
+            for &(key, chi, orientable, boundary_loops) in expected.iter() {
+                let s = stats(key, weld_seams);
+                assert_eq!(s.non_manifold_edges, 0, "surface type {}", key);
+                assert_eq!((s.euler_characteristic, s.orientable, s.boundary_loops), (chi, orientable, boundary_loops),
+                    "surface type {}", key);
+                assert_eq!(s.components, 1);
+            }
+        }
+        let torus = stats(21, false);
+        assert_eq!(torus.genus, 1);
+        assert!(torus.volume > 0.0);
+        let pear = stats(16, false);
+        assert_eq!(pear.genus, 0);
+        assert!(pear.volume > 0.0);
+        assert_eq!(stats(5, false).genus, 1);
+    }
+
+    // steiner and roman fold onto a segment, cross_cap and henneberg run into themselves along one. the
+    // sheets that meet there share their edges, so these are reported as not being manifolds.
+    #[test]
+    fn singular_surfaces_are_reported() {
+        for key in [9, 20, 28, 29] {
+            let s = stats(key, false);
+            assert!(s.non_manifold_edges > 0 && s.genus == -1, "surface type {}", key);
+        }
+    }
+}
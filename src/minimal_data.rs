@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+use cgmath::*;
+use num_complex::Complex32;
+use std::f32::consts::PI;
+use super::surface_data::{ISurfaceOutput, grid_indices, tangent_frame};
+
+pub type IComplexFunc = Box<dyn Fn(Complex32) -> Complex32>;
+
+// Weierstrass data (f, g) and the (u, v) domain of the presets. 0: catenoid, 1: Enneper,
+// 2: Scherk, 3: Richmond. catenoid and Richmond are written in w = ln z to stay away from z = 0.
+pub fn get_weierstrass_preset(key:u32) -> (IComplexFunc, IComplexFunc, [f32; 4]) {
+    match key {
+        1 => (Box::new(|_| Complex32::new(1.0, 0.0)), Box::new(|z| z), [-2.0, 2.0, -2.0, 2.0]),
+        2 => (Box::new(|z:Complex32| 4.0 / (1.0 - z.powu(4))), Box::new(|z| z), [-0.9, 0.9, -0.9, 0.9]),
+        3 => (Box::new(|w:Complex32| (-w).exp()), Box::new(|w:Complex32| (2.0 * w).exp()), [-1.0, 0.6, 0.0, 2.0 * PI]),
+        _ => (Box::new(|w:Complex32| (-w).exp()), Box::new(|w:Complex32| w.exp()), [-1.5, 1.5, 0.0, 2.0 * PI]),
+    }
+}
+
+// region: minimal surface
+pub struct IMinimalSurface {
+    pub f: IComplexFunc,
+    pub g: IComplexFunc, // Gauss map
+    pub umin: f32,
+    pub umax: f32,
+    pub vmin: f32,
+    pub vmax: f32,
+    pub u_resolution: u16,
+    pub v_resolution: u16,
+    pub angle: f32, // associate family angle: 0 for the catenoid and pi/2 for the helicoid
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub uv_lens: [f32; 2],
+}
+
+impl Default for IMinimalSurface {
+    fn default() -> Self {
+        let (f, g, [umin, umax, vmin, vmax]) = get_weierstrass_preset(0);
+        Self {
+            f,
+            g,
+            umin,
+            umax,
+            vmin,
+            vmax,
+            u_resolution: 48,
+            v_resolution: 48,
+            angle: 0.0,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            uv_lens: [1.0, 1.0],
+        }
+    }
+}
+
+impl IMinimalSurface {
+    pub fn set_preset(&mut self, key:u32) {
+        let (f, g, domain) = get_weierstrass_preset(key);
+        (self.f, self.g) = (f, g);
+        [self.umin, self.umax, self.vmin, self.vmax] = domain;
+    }
+
+    // x = Re(e^(i angle) * integral of phi dz) with phi = (f(1 - g^2)/2, i f(1 + g^2)/2, f g), integrated
+    // with Simpson's rule along the grid lines from (umin, vmin). the x3-axis of the classical
+    // representation becomes the y-axis.
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let (nu, nv) = (self.u_resolution as usize, self.v_resolution as usize);
+        let du = (self.umax - self.umin) / nu as f32;
+        let dv = (self.vmax - self.vmin) / nv as f32;
+        let zero = Complex32::new(0.0, 0.0);
+
+        // integrals of phi over the grid points: first along v = vmin, then up each column
+        let mut sums = vec![[zero; 3]; (nu + 1) * (nv + 1)];
+        for i in 0..=nu {
+            let z = Complex32::new(self.umin + du * i as f32, self.vmin);
+            if i > 0 {
+                sums[i * (nv + 1)] = self.segment(sums[(i - 1) * (nv + 1)], z - du, Complex32::new(du, 0.0));
+            }
+            for j in 1..=nv {
+                let z0 = z + Complex32::new(0.0, dv * (j - 1) as f32);
+                sums[j + i * (nv + 1)] = self.segment(sums[j - 1 + i * (nv + 1)], z0, Complex32::new(0.0, dv));
+            }
+        }
+
+        let rot = Complex32::from_polar(1.0, self.angle);
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=nu {
+            for j in 0..=nv {
+                let s = sums[j + i * (nv + 1)];
+                out.positions.push([(rot * s[0]).re, (rot * s[2]).re, (rot * s[1]).re]);
+
+                // exact tangents: x_u = Re(e^(i angle) phi) and x_v = -Im(e^(i angle) phi)
+                let phi = self.phi(Complex32::new(self.umin + du * i as f32, self.vmin + dv * j as f32));
+                let (p0, p1, p2) = (rot * phi[0], rot * phi[1], rot * phi[2]);
+                let xu = Vector3::new(p0.re, p2.re, p1.re);
+                let xv = -Vector3::new(p0.im, p2.im, p1.im);
+                let n = xv.cross(xu);
+                let n = if n.magnitude2() > 0.0 { n.normalize() } else { Vector3::unit_y() };
+                out.normals.push(n.into());
+                out.tangents.push(tangent_frame(xu, xv, n));
+
+                out.uvs.push([self.uv_lens[0] * i as f32 / nu as f32, self.uv_lens[1] * j as f32 / nv as f32]);
+            }
+        }
+        (out.indices, out.indices2) = grid_indices(nu as u32, nv as u32);
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+
+        out.normalize_positions(self.scale);
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        out
+    }
+
+    fn phi(&self, z:Complex32) -> [Complex32; 3] {
+        let (f, g) = ((self.f)(z), (self.g)(z));
+        let i = Complex32::new(0.0, 1.0);
+        [0.5 * f * (1.0 - g * g), 0.5 * i * f * (1.0 + g * g), f * g]
+    }
+
+    // start + integral of phi along the straight segment from z0 to z0 + h
+    fn segment(&self, start:[Complex32; 3], z0:Complex32, h:Complex32) -> [Complex32; 3] {
+        let (a, b, c) = (self.phi(z0), self.phi(z0 + 0.5 * h), self.phi(z0 + h));
+        let mut s = start;
+        for k in 0..3 {
+            s[k] += h / 6.0 * (a[k] + 4.0 * b[k] + c[k]);
+        }
+        s
+    }
+}
+// endregion: minimal surface
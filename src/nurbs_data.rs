@@ -0,0 +1,296 @@
+#![allow(dead_code)]
+use cgmath::*;
+use super::colormap;
+use super::surface_data::{ISurfaceOutput, grid_indices, tangent_frame};
+
+// clamped knot vector with uniformly spaced interior knots on [0, 1]. with degree = n - 1
+// this is the knot vector of a Bezier patch. with fewer than degree + 1 points there is no such
+// vector, and the one returned is too short for INurbsSurface::new to accept it.
+pub fn clamped_knots(n:usize, degree:usize) -> Vec<f32> {
+    let mut knots = vec![0.0f32; degree + 1];
+    let interior = n.saturating_sub(degree + 1);
+    for i in 1..=interior {
+        knots.push(i as f32 / (interior + 1) as f32);
+    }
+    knots.extend(vec![1.0f32; degree + 1]);
+    knots
+}
+
+// knot span index containing u (The NURBS Book, A2.1)
+fn find_span(n:usize, p:usize, u:f32, knots:&[f32]) -> usize {
+    if u >= knots[n + 1] {
+        return n;
+    }
+    if u <= knots[p] {
+        return p;
+    }
+    let (mut low, mut high) = (p, n + 1);
+    let mut mid = (low + high) / 2;
+    while u < knots[mid] || u >= knots[mid + 1] {
+        if u < knots[mid] { high = mid; } else { low = mid; }
+        mid = (low + high) / 2;
+    }
+    mid
+}
+
+// nonzero basis functions and their first derivatives at u (The NURBS Book, A2.3 with n = 1)
+fn basis_with_derivatives(span:usize, u:f32, p:usize, knots:&[f32]) -> (Vec<f32>, Vec<f32>) {
+    let mut ndu = vec![vec![0.0f32; p + 1]; p + 1];
+    let mut left = vec![0.0f32; p + 1];
+    let mut right = vec![0.0f32; p + 1];
+    ndu[0][0] = 1.0;
+    for j in 1..=p {
+        left[j] = u - knots[span + 1 - j];
+        right[j] = knots[span + j] - u;
+        let mut saved = 0.0;
+        for r in 0..j {
+            ndu[j][r] = right[r + 1] + left[j - r];
+            let temp = if ndu[j][r] != 0.0 { ndu[r][j - 1] / ndu[j][r] } else { 0.0 };
+            ndu[r][j] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        ndu[j][j] = saved;
+    }
+
+    let n:Vec<f32> = (0..=p).map(|j| ndu[j][p]).collect();
+    let mut dn = vec![0.0f32; p + 1];
+    if p > 0 {
+        for (r, d) in dn.iter_mut().enumerate() {
+            if r >= 1 {
+                *d += ndu[r - 1][p - 1] / ndu[p][r - 1];
+            }
+            if r < p {
+                *d -= ndu[r][p - 1] / ndu[p][r];
+            }
+            *d *= p as f32;
+        }
+    }
+    (n, dn)
+}
+
+// region: nurbs surface
+pub struct INurbsSurface {
+    pub control_points: Vec<Vec<[f32; 3]>>, // control_points[i][j], i along u and j along v
+    pub weights: Vec<Vec<f32>>, // empty for a non-rational B-spline surface
+    pub u_knots: Vec<f32>,
+    pub v_knots: Vec<f32>,
+    pub u_degree: usize,
+    pub v_degree: usize,
+    pub u_resolution: u16,
+    pub v_resolution: u16,
+    pub control_net: bool, // put the control net instead of the iso-lines into indices2
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub uv_lens: [f32; 2],
+}
+
+impl Default for INurbsSurface {
+    fn default() -> Self {
+        // a bicubic Bezier patch
+        let control_points:Vec<Vec<[f32; 3]>> = (0..4).map(|i| (0..4).map(|j| {
+            let (x, z) = (i as f32 - 1.5, j as f32 - 1.5);
+            let y = if (1..3).contains(&i) && (1..3).contains(&j) { 2.0 } else { 0.5 * (x - z).abs() - 1.0 };
+            [x, y, z]
+        }).collect()).collect();
+        Self {
+            control_points,
+            weights: vec![],
+            u_knots: clamped_knots(4, 3),
+            v_knots: clamped_knots(4, 3),
+            u_degree: 3,
+            v_degree: 3,
+            u_resolution: 32,
+            v_resolution: 32,
+            control_net: false,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            uv_lens: [1.0, 1.0],
+        }
+    }
+}
+
+impl INurbsSurface {
+    pub fn new(&mut self) -> anyhow::Result<ISurfaceOutput> {
+        self.validate()?;
+        let (nu, nv) = (self.control_points.len(), self.control_points[0].len());
+
+        let (umin, umax) = (self.u_knots[self.u_degree], self.u_knots[nu]);
+        let (vmin, vmax) = (self.v_knots[self.v_degree], self.v_knots[nv]);
+        let du = (umax - umin) / self.u_resolution as f32;
+        let dv = (vmax - vmin) / self.v_resolution as f32;
+
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=self.u_resolution {
+            let u = umin + du * i as f32;
+            for j in 0..=self.v_resolution {
+                let v = vmin + dv * j as f32;
+                let (pos, mut su, mut sv) = self.evaluate(u, v);
+                out.positions.push(pos.into());
+
+                // exact normals and tangents from the derivatives, stepping inside at collapsed edges
+                let mut normal = sv.cross(su);
+                if normal.magnitude2() < 1e-12 {
+                    let (u1, v1) = (u + (0.5 * (umin + umax) - u) * 1e-3, v + (0.5 * (vmin + vmax) - v) * 1e-3);
+                    (_, su, sv) = self.evaluate(u1, v1);
+                    normal = sv.cross(su);
+                }
+                let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { Vector3::unit_y() };
+                out.normals.push(normal.into());
+                out.tangents.push(tangent_frame(su, sv, normal));
+
+                out.uvs.push([self.uv_lens[0] * (u - umin) / (umax - umin), self.uv_lens[1] * (v - vmin) / (vmax - vmin)]);
+            }
+        }
+        (out.indices, out.indices2) = grid_indices(self.u_resolution as u32, self.v_resolution as u32);
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+
+        // the surface alone sets the size and the colormap range, and the control net follows it
+        let (center, factor) = out.normalize_positions(self.scale);
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+
+        if self.control_net {
+            let dir = self.colormap_direction as usize;
+            let (mut min_val, mut max_val) = (f32::MAX, f32::MIN);
+            for p in out.positions.iter() {
+                min_val = min_val.min(p[dir]);
+                max_val = max_val.max(p[dir]);
+            }
+            max_val = max_val.max(min_val + f32::EPSILON);
+            let cdata = colormap::colormap_data(&self.colormap_name);
+            let cdata2 = colormap::colormap_data(&self.wireframe_color);
+
+            let i0 = out.positions.len() as u32;
+            out.indices2.clear();
+            for i in 0..nu {
+                for j in 0..nv {
+                    let c = self.control_points[i][j];
+                    let p = [(c[0] - center[0]) * factor, (c[1] - center[1]) * factor, (c[2] - center[2]) * factor];
+                    out.positions.push(p);
+                    out.normals.push([0.0, 1.0, 0.0]);
+                    out.tangents.push([1.0, 0.0, 0.0, 1.0]);
+                    out.uvs.push([0.0, 0.0]);
+                    out.colors.push(colormap::color_lerp(cdata, min_val, max_val, p[dir]));
+                    out.colors2.push(colormap::color_lerp(cdata2, min_val, max_val, p[dir]));
+                    let idx = i0 + (j + i * nv) as u32;
+                    if j + 1 < nv {
+                        out.indices2.extend([idx, idx + 1]);
+                    }
+                    if i + 1 < nu {
+                        out.indices2.extend([idx, idx + nv as u32]);
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // check the net, the weights and the knots, which new evaluates without further bounds checks
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let nu = self.control_points.len();
+        let nv = self.control_points.first().map_or(0, |row| row.len());
+        if self.control_points.iter().any(|row| row.len() != nv) {
+            anyhow::bail!("control net rows must all have the same length");
+        }
+        if self.u_degree < 1 || self.v_degree < 1 {
+            anyhow::bail!("degrees must be at least 1, got {} and {}", self.u_degree, self.v_degree);
+        }
+        if nu <= self.u_degree || nv <= self.v_degree {
+            anyhow::bail!("a {}x{} control net needs at least {}x{} points", self.u_degree, self.v_degree,
+                self.u_degree + 1, self.v_degree + 1);
+        }
+        if self.control_points.iter().flatten().flatten().any(|c| !c.is_finite()) {
+            anyhow::bail!("control points must be finite");
+        }
+        if !self.weights.is_empty() {
+            if self.weights.len() != nu || self.weights.iter().any(|row| row.len() != nv) {
+                anyhow::bail!("weights must be empty or match the {}x{} control net", nu, nv);
+            }
+            if self.weights.iter().flatten().any(|w| !(w.is_finite() && *w > 0.0)) {
+                anyhow::bail!("weights must be finite and positive");
+            }
+        }
+        for (name, knots, n, degree) in [("u_knots", &self.u_knots, nu, self.u_degree), ("v_knots", &self.v_knots, nv, self.v_degree)] {
+            if knots.len() != n + degree + 1 {
+                anyhow::bail!("{} must have n + degree + 1 = {} entries, found {}", name, n + degree + 1, knots.len());
+            }
+            if knots.iter().any(|k| !k.is_finite()) || knots.windows(2).any(|k| k[1] < k[0]) || knots[degree] >= knots[n] {
+                anyhow::bail!("{} must be finite, non-decreasing and span a non-empty range", name);
+            }
+        }
+        if self.u_resolution == 0 || self.v_resolution == 0 {
+            anyhow::bail!("resolutions must be at least 1");
+        }
+        Ok(())
+    }
+
+    // surface point and its first partial derivatives at (u, v)
+    fn evaluate(&self, u:f32, v:f32) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        let (nu, nv) = (self.control_points.len(), self.control_points[0].len());
+        let (p, q) = (self.u_degree, self.v_degree);
+        let uspan = find_span(nu - 1, p, u, &self.u_knots);
+        let vspan = find_span(nv - 1, q, v, &self.v_knots);
+        let (nu_b, dnu_b) = basis_with_derivatives(uspan, u, p, &self.u_knots);
+        let (nv_b, dnv_b) = basis_with_derivatives(vspan, v, q, &self.v_knots);
+
+        // homogeneous sums: a = sum(N w P), w = sum(N w) and their derivatives
+        let zero = Vector3::new(0.0f32, 0.0, 0.0);
+        let (mut a, mut au, mut av) = (zero, zero, zero);
+        let (mut w, mut wu, mut wv) = (0.0f32, 0.0f32, 0.0f32);
+        for k in 0..=p {
+            for l in 0..=q {
+                let (i, j) = (uspan - p + k, vspan - q + l);
+                let wt = if self.weights.is_empty() { 1.0 } else { self.weights[i][j] };
+                let pt = Vector3::from(self.control_points[i][j]) * wt;
+                a += pt * (nu_b[k] * nv_b[l]);
+                au += pt * (dnu_b[k] * nv_b[l]);
+                av += pt * (nu_b[k] * dnv_b[l]);
+                w += wt * nu_b[k] * nv_b[l];
+                wu += wt * dnu_b[k] * nv_b[l];
+                wv += wt * nu_b[k] * dnv_b[l];
+            }
+        }
+        let s = a / w;
+        (s, (au - s * wu) / w, (av - s * wv) / w)
+    }
+}
+// endregion: nurbs surface
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_net_keeps_the_surface() {
+        let mut surface = INurbsSurface::default();
+        let plain = surface.new().unwrap();
+        surface.control_net = true;
+        let net = surface.new().unwrap();
+        let n = plain.positions.len();
+        assert!(net.positions.len() > n);
+        assert_eq!(&net.positions[..n], &plain.positions[..]);
+        assert_eq!(&net.colors[..n], &plain.colors[..]);
+        assert_eq!(net.colors.len(), net.positions.len());
+    }
+
+    #[test]
+    fn invalid_nets_are_rejected() {
+        let empty = INurbsSurface { control_points: vec![], ..Default::default() };
+        let ragged = INurbsSurface { control_points: vec![vec![[0.0; 3]; 4], vec![[0.0; 3]; 3], vec![[0.0; 3]; 4], vec![[0.0; 3]; 4]],
+            ..Default::default() };
+        // two points can't carry a cubic, and clamped_knots has no vector for them
+        let short = INurbsSurface { control_points: vec![vec![[0.0; 3]; 4]; 2], u_knots: clamped_knots(2, 3), ..Default::default() };
+        let knots = INurbsSurface { u_knots: vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0], ..Default::default() };
+        let decreasing = INurbsSurface { u_knots: vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.5, 1.0], ..Default::default() };
+        let weights = INurbsSurface { weights: vec![vec![1.0; 4]; 3], ..Default::default() };
+        let resolution = INurbsSurface { u_resolution: 0, ..Default::default() };
+        for (name, mut surface) in [("empty", empty), ("ragged", ragged), ("short", short), ("knots", knots),
+            ("decreasing", decreasing), ("weights", weights), ("resolution", resolution)] {
+            assert!(surface.new().is_err(), "{}", name);
+        }
+    }
+}
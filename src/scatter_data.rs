@@ -0,0 +1,359 @@
+#![allow(dead_code)]
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use super::colormap;
+use super::height_data::IHeightGrid;
+use super::surface_data::{IDirtyBuffers, ISurfaceOutput};
+
+// (x, z, y) samples from a CSV file, one point per line. lines that don't start with three
+// numbers, such as a header, are skipped.
+pub fn load_csv_points<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<[f32; 3]>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut pts: Vec<[f32; 3]> = vec![];
+    for line in text.lines() {
+        let values: Vec<f32> = line.split([',', ';', '\t'])
+            .map_while(|t| t.trim().parse().ok())
+            .collect();
+        if values.len() >= 3 {
+            pts.push([values[0], values[2], values[1]]);
+        }
+    }
+    if pts.len() < 3 {
+        anyhow::bail!("need at least 3 points, found {}", pts.len());
+    }
+    Ok(pts)
+}
+
+// region: delaunay triangulation
+fn circumcircle(a:[f64; 2], b:[f64; 2], c:[f64; 2]) -> ([f64; 2], f64) {
+    let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+    if d.abs() < 1e-300 {
+        return ([0.0, 0.0], f64::MAX);
+    }
+    let (a2, b2, c2) = (a[0]*a[0] + a[1]*a[1], b[0]*b[0] + b[1]*b[1], c[0]*c[0] + c[1]*c[1]);
+    let ux = (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d;
+    let uy = (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d;
+    let r2 = (a[0] - ux) * (a[0] - ux) + (a[1] - uy) * (a[1] - uy);
+    ([ux, uy], r2)
+}
+
+// 2D Delaunay triangulation (Bowyer-Watson). the triangles are counter-clockwise in the (x, z) plane,
+// duplicated points are left out.
+pub fn delaunay(pts:&[[f32; 2]]) -> Vec<[usize; 3]> {
+    let n = pts.len();
+    if n < 3 {
+        return vec![];
+    }
+    let mut p: Vec<[f64; 2]> = pts.iter().map(|q| [q[0] as f64, q[1] as f64]).collect();
+    let (mut min, mut max) = ([f64::MAX; 2], [f64::MIN; 2]);
+    for q in p.iter() {
+        for k in 0..2 {
+            min[k] = min[k].min(q[k]);
+            max[k] = max[k].max(q[k]);
+        }
+    }
+    let d = (max[0] - min[0]).max(max[1] - min[1]).max(1e-12);
+    let mid = [0.5 * (min[0] + max[0]), 0.5 * (min[1] + max[1])];
+
+    // super triangle enclosing all points
+    p.push([mid[0] - 20.0 * d, mid[1] - d]);
+    p.push([mid[0], mid[1] + 20.0 * d]);
+    p.push([mid[0] + 20.0 * d, mid[1] - d]);
+
+    let mut tris: Vec<([usize; 3], [f64; 2], f64)> = vec![];
+    let (s0, s1, s2) = (n, n + 1, n + 2);
+    let (c, r2) = circumcircle(p[s0], p[s1], p[s2]);
+    tris.push(([s0, s1, s2], c, r2));
+
+    let mut inserted: HashSet<(u64, u64)> = HashSet::new();
+    for i in 0..n {
+        let q = p[i];
+        if !inserted.insert((q[0].to_bits(), q[1].to_bits())) {
+            continue;
+        }
+        let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+        tris.retain(|(t, c, r2)| {
+            let inside = (q[0] - c[0]) * (q[0] - c[0]) + (q[1] - c[1]) * (q[1] - c[1]) < *r2;
+            if inside {
+                for e in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                    *edges.entry((e.0.min(e.1), e.0.max(e.1))).or_insert(0) += 1;
+                }
+            }
+            !inside
+        });
+        // the boundary of the cavity is made of the edges used once
+        for (&(a, b), &count) in edges.iter() {
+            if count == 1 {
+                let (c, r2) = circumcircle(p[a], p[b], q);
+                tris.push(([a, b, i], c, r2));
+            }
+        }
+    }
+
+    tris.iter()
+        .filter(|(t, _, _)| t.iter().all(|&k| k < n))
+        .map(|(t, _, _)| {
+            let (a, b, c) = (p[t[0]], p[t[1]], p[t[2]]);
+            let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+            if area < 0.0 { [t[0], t[2], t[1]] } else { *t }
+        })
+        .collect()
+}
+// endregion: delaunay triangulation
+
+// region: scattered data interpolation
+fn bounding_box(pts:&[[f32; 3]]) -> (f32, f32, f32, f32) {
+    let (mut xmin, mut xmax, mut zmin, mut zmax) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for p in pts.iter().filter(|p| p.iter().all(|c| c.is_finite())) {
+        xmin = xmin.min(p[0]);
+        xmax = xmax.max(p[0]);
+        zmin = zmin.min(p[2]);
+        zmax = zmax.max(p[2]);
+    }
+    (xmin, xmax, zmin, zmax)
+}
+
+// interpolate (x, y, z) samples onto an nx x nz height grid over their bounding box.
+// method 0: natural neighbour (discrete Sibson), 1: inverse distance weighting, 2: thin-plate spline RBF.
+// points with a non-finite coordinate are left out, since one would spoil every weight.
+pub fn interpolate_grid(pts:&[[f32; 3]], nx:usize, nz:usize, method:u32) -> anyhow::Result<IHeightGrid> {
+    let pts:Vec<[f32; 3]> = pts.iter().filter(|p| p.iter().all(|c| c.is_finite())).copied().collect();
+    let pts = &pts[..];
+    if pts.is_empty() {
+        anyhow::bail!("no finite points to interpolate");
+    }
+    if nx < 2 || nz < 2 {
+        anyhow::bail!("grid must be at least 2x2, got {}x{}", nx, nz);
+    }
+    let (xmin, xmax, zmin, zmax) = bounding_box(pts);
+    let dx = (xmax - xmin) / (nx - 1) as f32;
+    let dz = (zmax - zmin) / (nz - 1) as f32;
+    let xz = |i:usize, j:usize| (xmin + dx * i as f32, zmin + dz * j as f32);
+
+    let heights = match method {
+        0 => natural_neighbour(pts, nx, nz, &xz),
+        1 => (0..nx * nz).map(|k| {
+            let (x, z) = xz(k % nx, k / nx);
+            idw(pts, x, z, 2.0)
+        }).collect(),
+        _ => {
+            let weights = rbf_weights(pts);
+            (0..nx * nz).map(|k| {
+                let (x, z) = xz(k % nx, k / nx);
+                rbf_eval(pts, &weights, x, z)
+            }).collect()
+        }
+    };
+
+    Ok(IHeightGrid { nx, nz, heights, xmin, xmax, zmin, zmax })
+}
+
+pub fn idw(pts:&[[f32; 3]], x:f32, z:f32, power:f32) -> f32 {
+    let (mut sum, mut wsum) = (0.0f32, 0.0f32);
+    for p in pts.iter() {
+        let d2 = (p[0] - x) * (p[0] - x) + (p[2] - z) * (p[2] - z);
+        if d2 == 0.0 {
+            return p[1];
+        }
+        let w = d2.powf(-0.5 * power);
+        sum += w * p[1];
+        wsum += w;
+    }
+    sum / wsum
+}
+
+// discrete Sibson interpolation: every grid cell hands the value of its nearest sample to all the
+// cells closer to it than that sample, and each cell averages what it receives.
+fn natural_neighbour(pts:&[[f32; 3]], nx:usize, nz:usize, xz:&dyn Fn(usize, usize) -> (f32, f32)) -> Vec<f32> {
+    let mut sum = vec![0.0f32; nx * nz];
+    let mut count = vec![0u32; nx * nz];
+    let (x0, z0) = xz(0, 0);
+    let (x1, z1) = xz(1, 1);
+    let (dx, dz) = ((x1 - x0).max(f32::EPSILON), (z1 - z0).max(f32::EPSILON));
+
+    for j in 0..nz {
+        for i in 0..nx {
+            let (x, z) = xz(i, j);
+            let (mut best, mut value) = (f32::MAX, 0.0f32);
+            for p in pts.iter() {
+                let d2 = (p[0] - x) * (p[0] - x) + (p[2] - z) * (p[2] - z);
+                if d2 < best {
+                    best = d2;
+                    value = p[1];
+                }
+            }
+            let r = best.sqrt();
+            let (ri, rj) = ((r / dx).floor() as usize, (r / dz).floor() as usize);
+            for jj in j.saturating_sub(rj)..=(j + rj).min(nz - 1) {
+                for ii in i.saturating_sub(ri)..=(i + ri).min(nx - 1) {
+                    let (qx, qz) = xz(ii, jj);
+                    if (qx - x) * (qx - x) + (qz - z) * (qz - z) <= best {
+                        sum[ii + jj * nx] += value;
+                        count[ii + jj * nx] += 1;
+                    }
+                }
+            }
+        }
+    }
+    sum.iter().zip(count.iter()).map(|(s, &c)| s / c.max(1) as f32).collect()
+}
+
+fn thin_plate(r2:f64) -> f64 {
+    if r2 > 0.0 { 0.5 * r2 * r2.ln() } else { 0.0 }
+}
+
+// thin-plate spline weights plus the linear term [c0, cx, cz], solved by Gaussian elimination.
+// the dense solve is O(n^3), fine for up to a few thousand samples.
+fn rbf_weights(pts:&[[f32; 3]]) -> Vec<f64> {
+    let n = pts.len();
+    let m = n + 3;
+    let mut a = vec![0.0f64; m * m];
+    let mut b = vec![0.0f64; m];
+    for i in 0..n {
+        for j in 0..n {
+            let r2 = ((pts[i][0] - pts[j][0]) as f64).powi(2) + ((pts[i][2] - pts[j][2]) as f64).powi(2);
+            a[i * m + j] = thin_plate(r2);
+        }
+        let row = [1.0, pts[i][0] as f64, pts[i][2] as f64];
+        for k in 0..3 {
+            a[i * m + n + k] = row[k];
+            a[(n + k) * m + i] = row[k];
+        }
+        b[i] = pts[i][1] as f64;
+    }
+
+    for col in 0..m {
+        let pivot = (col..m).max_by(|&r1, &r2| a[r1 * m + col].abs().total_cmp(&a[r2 * m + col].abs())).unwrap();
+        if a[pivot * m + col].abs() < 1e-12 {
+            continue;
+        }
+        if pivot != col {
+            for k in 0..m {
+                a.swap(col * m + k, pivot * m + k);
+            }
+            b.swap(col, pivot);
+        }
+        for r in col + 1..m {
+            let f = a[r * m + col] / a[col * m + col];
+            if f != 0.0 {
+                for k in col..m {
+                    a[r * m + k] -= f * a[col * m + k];
+                }
+                b[r] -= f * b[col];
+            }
+        }
+    }
+    let mut w = vec![0.0f64; m];
+    for r in (0..m).rev() {
+        let s:f64 = (r + 1..m).map(|k| a[r * m + k] * w[k]).sum();
+        w[r] = if a[r * m + r].abs() < 1e-12 { 0.0 } else { (b[r] - s) / a[r * m + r] };
+    }
+    w
+}
+
+fn rbf_eval(pts:&[[f32; 3]], w:&[f64], x:f32, z:f32) -> f32 {
+    let n = pts.len();
+    let mut y = w[n] + w[n + 1] * x as f64 + w[n + 2] * z as f64;
+    for (i, p) in pts.iter().enumerate() {
+        let r2 = ((p[0] - x) as f64).powi(2) + ((p[2] - z) as f64).powi(2);
+        y += w[i] * thin_plate(r2);
+    }
+    y as f32
+}
+// endregion: scattered data interpolation
+
+// region: scatter surface
+// (x, y, z) samples triangulated in the x-z plane, normalized like ISimpleSurface
+pub struct IScatterSurface {
+    pub points: Vec<[f32; 3]>,
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub uv_lens: [f32; 2],
+}
+
+impl Default for IScatterSurface {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            scale: 1.0,
+            aspect_ratio: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            uv_lens: [1.0, 1.0],
+        }
+    }
+}
+
+impl IScatterSurface {
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let (xmin, xmax, zmin, zmax) = bounding_box(&self.points);
+        let finite:Vec<usize> = (0..self.points.len()).filter(|&i| self.points[i].iter().all(|c| c.is_finite())).collect();
+        let ymin = finite.iter().map(|&i| self.points[i][1]).fold(f32::MAX, f32::min);
+        let ymax = finite.iter().map(|&i| self.points[i][1]).fold(f32::MIN, f32::max).max(ymin + f32::EPSILON);
+
+        let mut output = ISurfaceOutput::default();
+        for p in self.points.iter() {
+            let x = (-1.0 + 2.0 * (p[0] - xmin) / (xmax - xmin)) * self.scale;
+            let y = (-1.0 + 2.0 * (p[1] - ymin) / (ymax - ymin)) * self.scale * self.aspect_ratio;
+            let z = (-1.0 + 2.0 * (p[2] - zmin) / (zmax - zmin)) * self.scale;
+            output.positions.push([x, y, z]);
+            output.uvs.push([self.uv_lens[0] * (p[0] - xmin) / (xmax - xmin),
+                self.uv_lens[1] * (p[2] - zmin) / (zmax - zmin)]);
+        }
+
+        // only the finite points are triangulated. counter-clockwise in (x, z) is clockwise seen from
+        // +y, so flip to face up
+        let pts2:Vec<[f32; 2]> = finite.iter().map(|&i| [self.points[i][0], self.points[i][2]]).collect();
+        for t in delaunay(&pts2) {
+            output.indices.extend([finite[t[0]] as u32, finite[t[2]] as u32, finite[t[1]] as u32]);
+        }
+        output.indices2 = output.edge_indices();
+        output.normals = vec![[0.0, 1.0, 0.0]; output.positions.len()];
+        output.reject_non_finite();
+        output.remove_unused_vertices();
+        output.compute_normals();
+        output.compute_tangents();
+
+        // colormap
+        let range = if self.colormap_direction == 1 { self.scale * self.aspect_ratio } else { self.scale };
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        for pos in output.positions.iter() {
+            output.colors.push(colormap::color_lerp(cdata, -range, range, pos[self.colormap_direction as usize]));
+            output.colors2.push(colormap::color_lerp(cdata2, -range, range, pos[self.colormap_direction as usize]));
+        }
+        output.dirty = IDirtyBuffers::all();
+        output
+    }
+}
+// endregion: scatter surface
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_points_are_left_out() {
+        let pts = vec![[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 2.0, 1.0], [1.0, 0.5, 1.0], [0.5, 1.5, 0.4]];
+        let mut spoiled = pts.clone();
+        spoiled.insert(2, [f32::NAN, 3.0, 0.5]);
+        spoiled.push([0.5, f32::INFINITY, 0.5]);
+        for method in 0..3 {
+            let clean = interpolate_grid(&pts, 8, 6, method).unwrap();
+            let grid = interpolate_grid(&spoiled, 8, 6, method).unwrap();
+            assert!(grid.heights.iter().all(|h| h.is_finite()), "method {}", method);
+            assert_eq!(grid.heights, clean.heights, "method {}", method);
+            assert_eq!((grid.xmin, grid.xmax, grid.zmin, grid.zmax), (0.0, 1.0, 0.0, 1.0));
+        }
+        assert!(interpolate_grid(&[[f32::NAN, 0.0, 0.0]], 4, 4, 1).is_err());
+
+        // a rebuilt scatter surface is uploaded whole, like the outputs update_time can't handle
+        let out = IScatterSurface { points: spoiled, ..Default::default() }.new();
+        assert_eq!(out.rejected, 2);
+        assert_eq!(out.dirty, IDirtyBuffers::all());
+    }
+}
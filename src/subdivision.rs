@@ -0,0 +1,394 @@
+#![allow(dead_code)]
+use cgmath::*;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+use super::surface_data::ISurfaceOutput;
+use super::vertex_data;
+
+// colors, wireframe colors and uvs, interpolated linearly during subdivision
+type Attr = [f32; 8];
+
+fn attr_lerp(a:&Attr, b:&Attr, t:f32) -> Attr {
+    let mut c = *a;
+    for k in 0..8 {
+        c[k] += (b[k] - a[k]) * t;
+    }
+    c
+}
+
+fn attr_average(attrs:&[&Attr]) -> Attr {
+    let mut c = [0.0f32; 8];
+    for a in attrs.iter() {
+        for k in 0..8 {
+            c[k] += a[k] / attrs.len() as f32;
+        }
+    }
+    c
+}
+
+fn attr_bits(a:&Attr) -> [u32; 8] {
+    a.map(|x| x.to_bits())
+}
+
+// polygon mesh with coincident vertices welded so that the faces share their edges. the attributes
+// are kept per face corner, so that faces meeting at a crease or seam (the faces of a cube, the
+// u = 0 and u = 1 columns of a grid) keep their own colors and uvs.
+struct IPolyMesh {
+    positions: Vec<Vector3<f32>>,
+    faces: Vec<Vec<usize>>,
+    corners: Vec<Vec<Attr>>,
+    normal_sign: f32, // 1 when the normals of the input follow the winding, -1 when they are opposite to it
+}
+
+impl IPolyMesh {
+    // weld vertices at the same position (seams, poles and the split faces of create_cube_data).
+    // with pair_quads, two consecutive triangles sharing an edge are merged back into a quad.
+    fn from_surface(mesh:&ISurfaceOutput, pair_quads:bool) -> Self {
+        // only the topology is welded, the attributes come from the original corner vertices
+        let mut welded = mesh.clone();
+        let remap = welded.weld_vertices();
+        let positions: Vec<Vector3<f32>> = welded.positions.iter().map(|p| Vector3::from(*p)).collect();
+
+        let attr = |i:usize| -> Attr {
+            let c = mesh.colors.get(i).copied().unwrap_or([1.0, 1.0, 1.0]);
+            let c2 = mesh.colors2.get(i).copied().unwrap_or([1.0, 1.0, 1.0]);
+            let uv = mesh.uvs.get(i).copied().unwrap_or([0.0, 0.0]);
+            [c[0], c[1], c[2], c2[0], c2[1], c2[2], uv[0], uv[1]]
+        };
+
+        // the triangles that collapse at a pole are dropped
+        let mut tris: Vec<([usize; 3], [Attr; 3])> = vec![];
+        let mut agreement = 0.0f32;
+        for t in mesh.indices.chunks(3) {
+            let p:Vec<Vector3<f32>> = t.iter().map(|&i| Vector3::from(mesh.positions[i as usize])).collect();
+            for &i in t {
+                if let Some(&n) = mesh.normals.get(i as usize) {
+                    agreement += (p[1] - p[0]).cross(p[2] - p[0]).dot(Vector3::from(n));
+                }
+            }
+            let v = [remap[t[0] as usize] as usize, remap[t[1] as usize] as usize, remap[t[2] as usize] as usize];
+            if v[0] != v[1] && v[1] != v[2] && v[2] != v[0] {
+                tris.push((v, [attr(t[0] as usize), attr(t[1] as usize), attr(t[2] as usize)]));
+            }
+        }
+
+        let mut faces: Vec<Vec<usize>> = vec![];
+        let mut corners: Vec<Vec<Attr>> = vec![];
+        let mut k = 0;
+        while k < tris.len() {
+            if pair_quads && k + 1 < tris.len() {
+                let ((t1, a1), (t2, a2)) = (&tris[k], &tris[k + 1]);
+                if let Some(quad) = merge_triangles(t1, t2) {
+                    // the vertex added from the second triangle takes its attribute from there
+                    let ca = quad.iter().map(|v| match t1.iter().position(|x| x == v) {
+                        Some(i) => a1[i],
+                        None => a2[t2.iter().position(|x| x == v).unwrap()],
+                    }).collect();
+                    faces.push(quad);
+                    corners.push(ca);
+                    k += 2;
+                    continue;
+                }
+            }
+            faces.push(tris[k].0.to_vec());
+            corners.push(tris[k].1.to_vec());
+            k += 1;
+        }
+        let normal_sign = if agreement < 0.0 { -1.0 } else { 1.0 };
+        Self { positions, faces, corners, normal_sign }
+    }
+
+    fn edge_faces(&self) -> HashMap<(usize, usize), Vec<usize>> {
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 0..face.len() {
+                let (a, b) = (face[i], face[(i + 1) % face.len()]);
+                edges.entry((a.min(b), a.max(b))).or_default().push(f);
+            }
+        }
+        edges
+    }
+
+    // neighbours of each vertex and, for boundary vertices, their boundary neighbours
+    fn neighbours(&self, edges:&HashMap<(usize, usize), Vec<usize>>) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut ring = vec![vec![]; self.positions.len()];
+        let mut boundary = vec![vec![]; self.positions.len()];
+        for (&(a, b), faces) in edges.iter() {
+            ring[a].push(b);
+            ring[b].push(a);
+            if faces.len() == 1 {
+                boundary[a].push(b);
+                boundary[b].push(a);
+            }
+        }
+        (ring, boundary)
+    }
+
+    // a vertex is emitted for every distinct attribute at a position, so creases and seams stay
+    // split. the normals are smoothed over the welded topology and keep the side of the input's.
+    fn into_surface(self) -> ISurfaceOutput {
+        let mut normals = vec![Vector3::new(0.0f32, 0.0, 0.0); self.positions.len()];
+        for face in self.faces.iter() {
+            for i in 1..face.len() - 1 {
+                let (p0, p1, p2) = (self.positions[face[0]], self.positions[face[i]], self.positions[face[i + 1]]);
+                let n = (p1 - p0).cross(p2 - p0) * self.normal_sign;
+                for k in [face[0], face[i], face[i + 1]] {
+                    normals[k] += n;
+                }
+            }
+        }
+
+        let mut out = ISurfaceOutput::default();
+        let mut split: HashMap<(usize, [u32; 8]), u32> = HashMap::new();
+        // polygons are fanned into triangles, the wireframe follows the polygon edges
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        for (face, ca) in self.faces.iter().zip(self.corners.iter()) {
+            let idx:Vec<u32> = face.iter().zip(ca.iter()).map(|(&v, a)| {
+                *split.entry((v, attr_bits(a))).or_insert_with(|| {
+                    let n = normals[v];
+                    out.positions.push(self.positions[v].into());
+                    out.normals.push(if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 1.0, 0.0] });
+                    out.colors.push([a[0], a[1], a[2]]);
+                    out.colors2.push([a[3], a[4], a[5]]);
+                    out.uvs.push([a[6], a[7]]);
+                    out.positions.len() as u32 - 1
+                })
+            }).collect();
+            for i in 1..face.len() - 1 {
+                out.indices.extend([idx[0], idx[i], idx[i + 1]]);
+            }
+            for i in 0..face.len() {
+                let (a, b) = (face[i], face[(i + 1) % face.len()]);
+                if edges.insert((a.min(b), a.max(b))) {
+                    out.indices2.extend([idx[i], idx[(i + 1) % face.len()]]);
+                }
+            }
+        }
+        out.compute_tangents();
+        out
+    }
+}
+
+// quad (a, b, d, c) from triangles (a, b, c) and (c, d, a) or any rotation of them sharing an edge
+fn merge_triangles(t1:&[usize; 3], t2:&[usize; 3]) -> Option<Vec<usize>> {
+    for i in 0..3 {
+        let (x, y) = (t1[i], t1[(i + 1) % 3]);
+        for j in 0..3 {
+            if t2[j] == y && t2[(j + 1) % 3] == x {
+                let d = t2[(j + 2) % 3];
+                let mut quad = t1.to_vec();
+                quad.insert(i + 1, d);
+                return Some(quad);
+            }
+        }
+    }
+    None
+}
+
+// one Catmull-Clark step: every n-gon becomes n quads
+fn catmull_clark_step(mesh:&IPolyMesh) -> IPolyMesh {
+    let nv = mesh.positions.len();
+    let edges = mesh.edge_faces();
+    let (ring, boundary) = mesh.neighbours(&edges);
+
+    let mut positions: Vec<Vector3<f32>> = vec![];
+
+    // face points
+    let face_start = nv;
+    let mut face_points: Vec<Vector3<f32>> = vec![];
+    for face in mesh.faces.iter() {
+        let c = face.iter().fold(Vector3::new(0.0, 0.0, 0.0), |c, &i| c + mesh.positions[i]) / face.len() as f32;
+        face_points.push(c);
+    }
+
+    // edge points
+    let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut edge_points: Vec<Vector3<f32>> = vec![];
+    let mut keys:Vec<&(usize, usize)> = edges.keys().collect();
+    keys.sort();
+    for &(a, b) in keys {
+        let faces = &edges[&(a, b)];
+        let mid = (mesh.positions[a] + mesh.positions[b]) * 0.5;
+        let p = if faces.len() == 2 {
+            (mid * 2.0 + face_points[faces[0]] + face_points[faces[1]]) * 0.25
+        } else {
+            mid
+        };
+        edge_index.insert((a, b), face_start + mesh.faces.len() + edge_points.len());
+        edge_points.push(p);
+    }
+
+    // vertex points
+    let mut vertex_faces = vec![vec![]; nv];
+    for (f, face) in mesh.faces.iter().enumerate() {
+        for &i in face.iter() {
+            vertex_faces[i].push(f);
+        }
+    }
+    for v in 0..nv {
+        let p = mesh.positions[v];
+        let moved = if !boundary[v].is_empty() {
+            if boundary[v].len() == 2 {
+                p * 0.75 + (mesh.positions[boundary[v][0]] + mesh.positions[boundary[v][1]]) * 0.125
+            } else {
+                p // corner
+            }
+        } else if ring[v].is_empty() {
+            p
+        } else {
+            let n = ring[v].len() as f32;
+            let f = vertex_faces[v].iter().fold(Vector3::new(0.0, 0.0, 0.0), |c, &k| c + face_points[k]) / vertex_faces[v].len() as f32;
+            let r = ring[v].iter().fold(Vector3::new(0.0, 0.0, 0.0), |c, &k| c + (p + mesh.positions[k]) * 0.5) / n;
+            (f + r * 2.0 + p * (n - 3.0)) / n
+        };
+        positions.push(moved);
+    }
+    positions.extend(face_points);
+    positions.extend(edge_points);
+
+    // the corner attributes are interpolated within each face
+    let mut faces: Vec<Vec<usize>> = vec![];
+    let mut corners: Vec<Vec<Attr>> = vec![];
+    for (f, (face, ca)) in mesh.faces.iter().zip(mesh.corners.iter()).enumerate() {
+        let n = face.len();
+        let e = |i:usize| {
+            let (a, b) = (face[i % n], face[(i + 1) % n]);
+            edge_index[&(a.min(b), a.max(b))]
+        };
+        let mid = |i:usize| attr_lerp(&ca[i % n], &ca[(i + 1) % n], 0.5);
+        let center = attr_average(&ca.iter().collect::<Vec<_>>());
+        for (i, &v) in face.iter().enumerate() {
+            faces.push(vec![v, e(i), face_start + f, e(i + n - 1)]);
+            corners.push(vec![ca[i], mid(i), center, mid(i + n - 1)]);
+        }
+    }
+    IPolyMesh { positions, faces, corners, normal_sign: mesh.normal_sign }
+}
+
+// one Loop step: every triangle becomes 4 triangles
+fn loop_step(mesh:&IPolyMesh) -> IPolyMesh {
+    let nv = mesh.positions.len();
+    let edges = mesh.edge_faces();
+    let (ring, boundary) = mesh.neighbours(&edges);
+
+    let mut positions: Vec<Vector3<f32>> = vec![];
+    for v in 0..nv {
+        let p = mesh.positions[v];
+        let moved = if !boundary[v].is_empty() {
+            if boundary[v].len() == 2 {
+                p * 0.75 + (mesh.positions[boundary[v][0]] + mesh.positions[boundary[v][1]]) * 0.125
+            } else {
+                p
+            }
+        } else if ring[v].is_empty() {
+            p
+        } else {
+            let n = ring[v].len() as f32;
+            let beta = (0.625 - (0.375 + 0.25 * (2.0 * PI / n).cos()).powi(2)) / n;
+            let sum = ring[v].iter().fold(Vector3::new(0.0, 0.0, 0.0), |c, &k| c + mesh.positions[k]);
+            p * (1.0 - n * beta) + sum * beta
+        };
+        positions.push(moved);
+    }
+
+    // the vertex of a triangle opposite to edge (a, b)
+    let opposite = |f:usize, a:usize, b:usize| *mesh.faces[f].iter().find(|&&k| k != a && k != b).unwrap();
+
+    let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut keys:Vec<&(usize, usize)> = edges.keys().collect();
+    keys.sort();
+    for &(a, b) in keys {
+        let faces = &edges[&(a, b)];
+        let mid = (mesh.positions[a] + mesh.positions[b]) * 0.5;
+        let p = if faces.len() == 2 {
+            let (c, d) = (opposite(faces[0], a, b), opposite(faces[1], a, b));
+            mid * 0.75 + (mesh.positions[c] + mesh.positions[d]) * 0.125
+        } else {
+            mid
+        };
+        edge_index.insert((a, b), positions.len());
+        positions.push(p);
+    }
+
+    let mut faces: Vec<Vec<usize>> = vec![];
+    let mut corners: Vec<Vec<Attr>> = vec![];
+    for (face, t) in mesh.faces.iter().zip(mesh.corners.iter()) {
+        let e = |a:usize, b:usize| edge_index[&(a.min(b), a.max(b))];
+        let (a, b, c) = (face[0], face[1], face[2]);
+        let (ab, bc, ca) = (e(a, b), e(b, c), e(c, a));
+        faces.extend([vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]);
+        let (tab, tbc, tca) = (attr_lerp(&t[0], &t[1], 0.5), attr_lerp(&t[1], &t[2], 0.5), attr_lerp(&t[2], &t[0], 0.5));
+        corners.extend([vec![t[0], tab, tca], vec![tab, t[1], tbc], vec![tca, tbc, t[2]], vec![tab, tbc, tca]]);
+    }
+    IPolyMesh { positions, faces, corners, normal_sign: mesh.normal_sign }
+}
+
+// Catmull-Clark subdivision. consecutive triangle pairs of grid meshes are treated as quads.
+pub fn catmull_clark(mesh:&ISurfaceOutput, levels:u32) -> ISurfaceOutput {
+    let mut poly = IPolyMesh::from_surface(mesh, true);
+    for _ in 0..levels {
+        poly = catmull_clark_step(&poly);
+    }
+    poly.into_surface()
+}
+
+// Loop subdivision of a triangle mesh
+pub fn loop_subdivision(mesh:&ISurfaceOutput, levels:u32) -> ISurfaceOutput {
+    let mut poly = IPolyMesh::from_surface(mesh, false);
+    for _ in 0..levels {
+        poly = loop_step(&poly);
+    }
+    poly.into_surface()
+}
+
+// create_cube_data as an ISurfaceOutput, a convenient coarse control shape
+pub fn cube_surface_data(side:f32) -> ISurfaceOutput {
+    let (positions, colors, normals, uvs, indices, indices2) = vertex_data::create_cube_data(side);
+    let colors2 = vec![[1.0, 1.0, 1.0]; positions.len()];
+    ISurfaceOutput { positions, normals, colors, colors2, uvs, indices, indices2, ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::surface_data::IParametricSurface;
+    use super::*;
+
+    #[test]
+    fn cube_faces_keep_their_uvs() {
+        let cube = cube_surface_data(2.0);
+        for out in [catmull_clark(&cube, 2), loop_subdivision(&cube, 2)] {
+            assert!(out.indices.iter().chain(out.indices2.iter()).all(|&i| (i as usize) < out.positions.len()));
+            // every face still maps onto the whole unit square, none is smeared by its neighbours
+            let mut area = 0.0;
+            for t in out.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| out.uvs[t[k] as usize]);
+                let at = ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() * 0.5;
+                assert!(at > 0.0);
+                area += at;
+            }
+            assert!((area - 6.0).abs() < 1e-4);
+            // the faces still share their edges
+            let mut welded = out.clone();
+            welded.weld_vertices();
+            assert!(welded.positions.len() < out.positions.len());
+        }
+    }
+
+    // the generators' normals are opposite to the winding, the ones of create_cube_data follow it
+    #[test]
+    fn normals_keep_the_side_of_the_input() {
+        let torus = IParametricSurface { surface_type: 21, u_resolution: 16, v_resolution: 16, ..Default::default() }.new();
+        for out in [catmull_clark(&torus, 1), loop_subdivision(&torus, 1)] {
+            for (p, n) in out.positions.iter().zip(out.normals.iter()) {
+                let d = |k:usize| (Vector3::from(torus.positions[k]) - Vector3::from(*p)).magnitude2();
+                let nearest = (0..torus.positions.len()).min_by(|&a, &b| d(a).total_cmp(&d(b))).unwrap();
+                assert!(Vector3::from(*n).dot(Vector3::from(torus.normals[nearest])) > 0.0);
+            }
+        }
+        // the cube's normals point outwards, away from its center
+        let cube = cube_surface_data(2.0);
+        for out in [catmull_clark(&cube, 1), loop_subdivision(&cube, 1)] {
+            assert!(out.positions.iter().zip(out.normals.iter()).all(|(p, n)| Vector3::from(*p).dot(Vector3::from(*n)) > 0.0));
+        }
+    }
+}
@@ -1,21 +1,444 @@
 #![allow(dead_code)]
 use cgmath::*;
 use std::f32::consts::PI;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use num_complex::Complex32;
+use rayon::prelude::*;
 use super::colormap;
 use super::math_func as mf;
+use super::height_data::IHeightGrid;
+use super::curve_data::{perpendicular, is_closed, rotation_minimizing_frames};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ISurfaceOutput {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
     pub colors: Vec<[f32; 3]>,
     pub colors2: Vec<[f32; 3]>,
     pub uvs: Vec<[f32; 2]>,
-    pub indices: Vec<u16>,
-    pub indices2: Vec<u16>,
+    pub indices: Vec<u32>,
+    pub indices2: Vec<u32>,
+    pub tangents: Vec<[f32; 4]>, // unit tangent along the u-direction of the uvs, w: handedness of the bitangent
+    pub rejected: usize, // samples dropped because their position was not finite
+    pub dirty: IDirtyBuffers, // buffers changed since the caller last uploaded them and cleared this
 } 
 
+// buffers of an ISurfaceOutput that were rewritten, so that only those need to be uploaded again
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IDirtyBuffers {
+    pub positions: bool,
+    pub normals: bool,
+    pub tangents: bool,
+    pub colors: bool,
+    pub colors2: bool,
+    pub uvs: bool,
+    pub indices: bool,
+    pub indices2: bool,
+}
+
+impl IDirtyBuffers {
+    pub fn all() -> Self {
+        Self { positions: true, normals: true, tangents: true, colors: true, colors2: true, uvs: true, indices: true, indices2: true }
+    }
+
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+
+    pub fn merge(&mut self, other:IDirtyBuffers) {
+        self.positions |= other.positions;
+        self.normals |= other.normals;
+        self.tangents |= other.tangents;
+        self.colors |= other.colors;
+        self.colors2 |= other.colors2;
+        self.uvs |= other.uvs;
+        self.indices |= other.indices;
+        self.indices2 |= other.indices2;
+    }
+}
+
+impl ISurfaceOutput {
+    // center the positions and fit them into [-scale, scale] like parametric_surface_range does.
+    // returns the center and the factor, so that p -> (p - center) * factor maps other points alike.
+    pub fn normalize_positions(&mut self, scale:f32) -> ([f32; 3], f32) {
+        let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in self.positions.iter() {
+            for k in 0..3 {
+                pmin[k] = pmin[k].min(p[k]);
+                pmax[k] = pmax[k].max(p[k]);
+            }
+        }
+        let dist = (pmax[0] - pmin[0]).max(pmax[1] - pmin[1]).max(pmax[2] - pmin[2]);
+        if dist <= 0.0 {
+            return ([0.0; 3], 1.0);
+        }
+        let center = [0.5 * (pmin[0] + pmax[0]), 0.5 * (pmin[1] + pmax[1]), 0.5 * (pmin[2] + pmax[2])];
+        let factor = scale / dist;
+        for p in self.positions.iter_mut() {
+            for k in 0..3 {
+                p[k] = (p[k] - center[k]) * factor;
+            }
+        }
+        (center, factor)
+    }
+
+    // drop the triangles and wireframe segments that touch an invalid vertex. invalid vertices are
+    // parked at the origin, and normals spoiled by a neighbouring hole are rebuilt from the faces left.
+    pub fn cut_holes(&mut self, valid:&[bool]) {
+        let keep = |idx:&[u32]| idx.iter().all(|&i| valid[i as usize]);
+        self.indices = self.indices.chunks(3).filter(|t| keep(t)).flatten().copied().collect();
+        self.indices2 = self.indices2.chunks(2).filter(|l| keep(l)).flatten().copied().collect();
+
+        let mut face_normals = vec![Vector3::new(0.0f32, 0.0, 0.0); self.positions.len()];
+        for t in self.indices.chunks(3) {
+            let p:Vec<Vector3<f32>> = t.iter().map(|&i| Vector3::from(self.positions[i as usize])).collect();
+            let n = (p[1] - p[0]).cross(p[2] - p[0]);
+            for &i in t {
+                face_normals[i as usize] += n;
+            }
+        }
+
+        for i in 0..self.positions.len() {
+            if !valid[i] {
+                self.positions[i] = [0.0, 0.0, 0.0];
+                self.normals[i] = [0.0, 1.0, 0.0];
+                // colors may still be missing when a generator colormaps afterwards
+                if let (Some(c), Some(c2)) = (self.colors.get_mut(i), self.colors2.get_mut(i)) {
+                    (*c, *c2) = ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+                }
+            } else if !self.normals[i].iter().all(|c| c.is_finite()) {
+                let n = face_normals[i];
+                // keep the orientation of the generator's normals, which is opposite to the winding
+                self.normals[i] = if n.magnitude2() > 0.0 { (-n.normalize()).into() } else { [0.0, 1.0, 0.0] };
+            }
+            if let Some(t) = self.tangents.get_mut(i) {
+                if !t.iter().all(|c| c.is_finite()) {
+                    let p = perpendicular(Vector3::from(self.normals[i]));
+                    *t = [p.x, p.y, p.z, 1.0];
+                }
+            }
+        }
+    }
+
+    // drop the triangles and wireframe segments at samples with a non-finite position and count them
+    // in rejected. non-finite normals at finite positions, such as at a collapsed pole, are rebuilt
+    // from the neighbouring faces instead, so that the surface doesn't open up there.
+    pub fn reject_non_finite(&mut self) {
+        let finite = |p:&[f32; 3]| p.iter().all(|c| c.is_finite());
+        self.rejected = self.positions.iter().filter(|p| !finite(p)).count();
+        if self.rejected > 0 || !self.normals.iter().all(finite) {
+            let valid:Vec<bool> = self.positions.iter().map(finite).collect();
+            self.cut_holes(&valid);
+        }
+    }
+
+    // empty the vertex attributes and refill them with n default vertices, keeping the allocations
+    pub(crate) fn reset_vertices(&mut self, n:usize) {
+        fn reset<T:Copy>(v:&mut Vec<T>, n:usize, value:T) {
+            v.clear();
+            v.resize(n, value);
+        }
+        reset(&mut self.positions, n, [0.0; 3]);
+        reset(&mut self.normals, n, [0.0, 1.0, 0.0]);
+        reset(&mut self.colors, n, [0.0; 3]);
+        reset(&mut self.colors2, n, [0.0; 3]);
+        reset(&mut self.uvs, n, [0.0; 2]);
+        reset(&mut self.tangents, n, [1.0, 0.0, 0.0, 1.0]);
+        self.rejected = 0;
+    }
+
+    // drop the vertices that no triangle or wireframe segment refers to, such as the duplicated seam
+    // of a periodic grid
+    pub fn remove_unused_vertices(&mut self) {
+        let mut used = vec![false; self.positions.len()];
+        for &i in self.indices.iter().chain(self.indices2.iter()) {
+            used[i as usize] = true;
+        }
+        let mut remap = vec![0u32; self.positions.len()];
+        let mut count = 0;
+        for i in 0..used.len() {
+            remap[i] = count;
+            count += used[i] as u32;
+        }
+        if count as usize == used.len() {
+            return;
+        }
+        // compact in place so that the buffers keep their capacity
+        fn keep<T>(v:&mut Vec<T>, used:&[bool]) {
+            let mut k = used.iter();
+            v.retain(|_| *k.next().unwrap_or(&true));
+        }
+        keep(&mut self.positions, &used);
+        keep(&mut self.normals, &used);
+        keep(&mut self.colors, &used);
+        keep(&mut self.colors2, &used);
+        keep(&mut self.uvs, &used);
+        keep(&mut self.tangents, &used);
+        for i in self.indices.iter_mut().chain(self.indices2.iter_mut()) {
+            *i = remap[*i as usize];
+        }
+    }
+
+    // merge the vertices at the same position, up to a tolerance relative to the size of the mesh, so
+    // that seams and poles share their vertices. normals that agree are averaged, the other attributes
+    // are taken from the first vertex. collapsed triangles and repeated wireframe segments are dropped.
+    // returns the new index of every old vertex.
+    pub fn weld_vertices(&mut self) -> Vec<u32> {
+        let (mut pmin, mut pmax) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in self.positions.iter() {
+            for k in 0..3 {
+                pmin[k] = pmin[k].min(p[k]);
+                pmax[k] = pmax[k].max(p[k]);
+            }
+        }
+        let tol = 1e-5 * (pmax[0] - pmin[0]).max(pmax[1] - pmin[1]).max(pmax[2] - pmin[2]).max(f32::EPSILON);
+
+        // vertices are hashed into cells of size tol, and the neighbouring cells are searched as well so
+        // that two close vertices on either side of a cell border still meet
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut remap: Vec<u32> = vec![];
+        let mut first: Vec<usize> = vec![];
+        for (i, p) in self.positions.iter().enumerate() {
+            let key = [(p[0] / tol).floor() as i64, (p[1] / tol).floor() as i64, (p[2] / tol).floor() as i64];
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        for &k in cells.get(&[key[0] + dx, key[1] + dy, key[2] + dz]).into_iter().flatten() {
+                            let q = self.positions[first[k as usize]];
+                            if (0..3).all(|c| (p[c] - q[c]).abs() <= tol) {
+                                found = Some(k);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            let idx = found.unwrap_or_else(|| {
+                first.push(i);
+                cells.entry(key).or_default().push(first.len() as u32 - 1);
+                first.len() as u32 - 1
+            });
+            remap.push(idx);
+        }
+
+        if self.normals.len() == self.positions.len() {
+            let mut normals:Vec<Vector3<f32>> = first.iter().map(|&i| Vector3::from(self.normals[i])).collect();
+            for (i, &k) in remap.iter().enumerate() {
+                let n = Vector3::from(self.normals[i]);
+                // a twisted seam, as in the Mobius strip, meets with opposite normals: keep the first one
+                if i != first[k as usize] && n.dot(Vector3::from(self.normals[first[k as usize]])) > 0.0 {
+                    normals[k as usize] += n;
+                }
+            }
+            self.normals = normals.iter()
+                .map(|n| if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 1.0, 0.0] })
+                .collect();
+        }
+        fn pick<T:Copy>(v:&[T], first:&[usize]) -> Vec<T> {
+            if v.is_empty() { vec![] } else { first.iter().map(|&i| v[i]).collect() }
+        }
+        self.positions = pick(&self.positions, &first);
+        self.colors = pick(&self.colors, &first);
+        self.colors2 = pick(&self.colors2, &first);
+        self.uvs = pick(&self.uvs, &first);
+        self.tangents = pick(&self.tangents, &first);
+
+        self.indices = self.indices.chunks(3)
+            .map(|t| [remap[t[0] as usize], remap[t[1] as usize], remap[t[2] as usize]])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .flatten().collect();
+        self.indices2 = unique_segments(self.indices2.iter().map(|&i| remap[i as usize]));
+        remap
+    }
+
+    // area-weighted vertex normals facing the same way as the triangle winding
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vector3::new(0.0f32, 0.0, 0.0); self.positions.len()];
+        for t in self.indices.chunks(3) {
+            let p:Vec<Vector3<f32>> = t.iter().map(|&i| Vector3::from(self.positions[i as usize])).collect();
+            let n = (p[1] - p[0]).cross(p[2] - p[0]);
+            for &i in t {
+                normals[i as usize] += n;
+            }
+        }
+        self.normals = normals.iter()
+            .map(|n| if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 1.0, 0.0] })
+            .collect();
+    }
+
+    // tangents from the uv gradients of the triangles, weighted by their corner angles as in
+    // MikkTSpace, made orthogonal to the normals. the handedness w tells whether the bitangent
+    // w * cross(n, t) runs along increasing v.
+    pub fn compute_tangents(&mut self) {
+        let zero = Vector3::new(0.0f32, 0.0, 0.0);
+        let (mut ts, mut bs) = (vec![zero; self.positions.len()], vec![zero; self.positions.len()]);
+        if self.uvs.len() == self.positions.len() {
+            for t in self.indices.chunks(3) {
+                for k in 0..3 {
+                    let (i0, i1, i2) = (t[k] as usize, t[(k + 1) % 3] as usize, t[(k + 2) % 3] as usize);
+                    let p0 = Vector3::from(self.positions[i0]);
+                    let (e1, e2) = (Vector3::from(self.positions[i1]) - p0, Vector3::from(self.positions[i2]) - p0);
+                    let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+                    let (du1, dv1, du2, dv2) = (uv1[0] - uv0[0], uv1[1] - uv0[1], uv2[0] - uv0[0], uv2[1] - uv0[1]);
+                    let det = du1 * dv2 - du2 * dv1;
+                    if det.abs() < 1e-12 || e1.magnitude2() == 0.0 || e2.magnitude2() == 0.0 {
+                        continue;
+                    }
+                    let angle = e1.normalize().dot(e2.normalize()).clamp(-1.0, 1.0).acos();
+                    ts[i0] += (e1 * dv2 - e2 * dv1) * (angle / det);
+                    bs[i0] += (e2 * du1 - e1 * du2) * (angle / det);
+                }
+            }
+        }
+        self.tangents = (0..self.positions.len()).map(|i| {
+            let n = self.normals.get(i).map_or(Vector3::unit_y(), |&n| Vector3::from(n));
+            tangent_frame(ts[i], bs[i], n)
+        }).collect();
+    }
+
+    // wireframe line list with every triangle edge used once
+    pub fn edge_indices(&self) -> Vec<u32> {
+        let mut edges: HashSet<(u32, u32)> = HashSet::new();
+        let mut indices2: Vec<u32> = vec![];
+        for t in self.indices.chunks(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                if edges.insert((a.min(b), a.max(b))) {
+                    indices2.extend([a, b]);
+                }
+            }
+        }
+        indices2
+    }
+
+    // colormap the vertices along colormap_direction (0: x, 1: y, 2: z) over their full range
+    pub fn set_colormap(&mut self, colormap_name:&str, wireframe_color:&str, colormap_direction:u32) {
+        let dir = colormap_direction as usize;
+        let (mut min_val, mut max_val) = (f32::MAX, f32::MIN);
+        for p in self.positions.iter() {
+            min_val = min_val.min(p[dir]);
+            max_val = max_val.max(p[dir]);
+        }
+        max_val = max_val.max(min_val + f32::EPSILON);
+        let cdata = colormap::colormap_data(colormap_name);
+        let cdata2 = colormap::colormap_data(wireframe_color);
+        self.colors = self.positions.iter().map(|p| colormap::color_lerp(cdata, min_val, max_val, p[dir])).collect();
+        self.colors2 = self.positions.iter().map(|p| colormap::color_lerp(cdata2, min_val, max_val, p[dir])).collect();
+    }
+}
+
+// tangent along du made orthogonal to the normal n, with w = 1 when the bitangent cross(n, t) runs
+// along dv and -1 otherwise. a degenerate du gives any tangent perpendicular to n.
+pub(crate) fn tangent_frame(du:Vector3<f32>, dv:Vector3<f32>, n:Vector3<f32>) -> [f32; 4] {
+    let t = du - n * n.dot(du);
+    let t = if t.magnitude2() > 1e-20 && t.x.is_finite() && t.y.is_finite() && t.z.is_finite() {
+        t.normalize()
+    } else {
+        perpendicular(n)
+    };
+    let w = if n.cross(t).dot(dv) < 0.0 { -1.0 } else { 1.0 };
+    [t.x, t.y, t.z, w]
+}
+
+// triangle and wireframe indices for a (nu + 1) x (nv + 1) vertex grid stored row by row
+pub(crate) fn grid_indices(nu:u32, nv:u32) -> (Vec<u32>, Vec<u32>) {
+    let (mut indices, mut indices2) = (vec![], vec![]);
+    grid_indices_into(nu, nv, [0, 0], &mut indices, &mut indices2);
+    (indices, indices2)
+}
+
+// grid_indices for a grid whose seams are glued: seams[0] for the u-direction and seams[1] for the
+// v-direction, with 0: open, 1: periodic, 2: twisted, 3: twisted about the middle of the other
+// (periodic) parameter and only used in the u-direction. the last column (row) is replaced by the first
+// one, reversed along a twisted seam, which leaves the duplicated vertices unused.
+pub(crate) fn periodic_grid_indices(nu:u32, nv:u32, seams:[u32; 2]) -> (Vec<u32>, Vec<u32>) {
+    let (mut indices, mut indices2) = (vec![], vec![]);
+    grid_indices_into(nu, nv, seams, &mut indices, &mut indices2);
+    (indices, indices2)
+}
+
+// periodic_grid_indices written into existing buffers, so that regenerating a surface every frame
+// reuses their allocations
+pub(crate) fn grid_indices_into(nu:u32, nv:u32, seams:[u32; 2], indices:&mut Vec<u32>, indices2:&mut Vec<u32>) {
+    // the middle of the v-range is only a grid line for an even nv
+    let seams = if seams[0] == 3 && nv % 2 == 1 { [0, seams[1]] } else { seams };
+    let wrap_u = |i:u32, j:u32| if i == nu && seams[0] > 0 {
+        (0, match seams[0] { 2 => nv - j, 3 => (3 * nv / 2 - j) % nv, _ => j })
+    } else { (i, j) };
+    let wrap_v = |i:u32, j:u32| if j == nv && seams[1] > 0 { (if seams[1] == 2 { nu - i } else { i }, 0) } else { (i, j) };
+    let wrap = |i:u32, j:u32| {
+        let (i, j) = wrap_u(i, j);
+        let (i, j) = wrap_v(i, j);
+        // a corner can land on the u-seam again
+        let (i, j) = wrap_u(i, j);
+        j + i * (nv + 1)
+    };
+    let on_border = |i:u32, j:u32| i == 0 || i == nu || j == 0 || j == nv;
+
+    indices.clear();
+    indices2.clear();
+    indices.reserve(6 * nu as usize * nv as usize);
+    indices2.reserve(4 * nu as usize * nv as usize + 2 * (nu + nv) as usize);
+    // only segments running along the border can repeat once the seams are glued, so only those are
+    // checked against the ones seen before
+    let mut seen: HashSet<(u32, u32)> = HashSet::new();
+    let mut segment = |indices2:&mut Vec<u32>, a:(u32, u32), b:(u32, u32)| {
+        let (ia, ib) = (wrap(a.0, a.1), wrap(b.0, b.1));
+        if ia != ib && (!(on_border(a.0, a.1) && on_border(b.0, b.1)) || seen.insert((ia.min(ib), ia.max(ib)))) {
+            indices2.extend([ia, ib]);
+        }
+    };
+
+    for i in 0..nu {
+        for j in 0..nv {
+            let idx0 = wrap(i, j);
+            let idx1 = wrap(i, j + 1);
+            let idx2 = wrap(i + 1, j + 1);
+            let idx3 = wrap(i + 1, j);
+            indices.extend([idx0, idx1, idx2, idx2, idx3, idx0]);
+
+            segment(indices2, (i, j), (i, j + 1));
+            segment(indices2, (i, j), (i + 1, j));
+            // the last row and column close the grid
+            if j == nv - 1 {
+                segment(indices2, (i, j + 1), (i + 1, j + 1));
+            }
+            if i == nu - 1 {
+                segment(indices2, (i + 1, j + 1), (i + 1, j));
+            }
+        }
+    }
+}
+
+// line list without repeated or zero-length segments
+pub(crate) fn unique_segments(indices2:impl Iterator<Item = u32>) -> Vec<u32> {
+    let pts:Vec<u32> = indices2.collect();
+    let mut edges: HashSet<(u32, u32)> = HashSet::new();
+    let mut out: Vec<u32> = vec![];
+    for l in pts.chunks(2) {
+        if l[0] != l[1] && edges.insert((l[0].min(l[1]), l[0].max(l[1]))) {
+            out.extend([l[0], l[1]]);
+        }
+    }
+    out
+}
+
+// seams of the (u, v) domains set in IParametricSurface::new: [u, v] with 0: open, 1: periodic,
+// 2: twisted, where the seam is glued with the other parameter reversed as in the Mobius strip, and
+// 3: twisted about the middle of the other parameter as in klein_bottle
+pub fn get_surface_periodicity(key:u32) -> [u32; 2] {
+    match key {
+        4 | 12 | 21 | 30 | 31 | 32 => [1, 1],
+        2 | 9 | 10 | 16 | 17 | 18 => [0, 1],
+        1 | 3 | 20 | 25 | 28 | 29 => [1, 0],
+        5 => [1, 2],
+        8 => [2, 1],
+        23 => [2, 0],
+        0 | 11 => [3, 1],
+        _ => [0, 0],
+    }
+}
+
 // region: parametric surface
 pub struct IParametricSurface {
     pub surface_type: u32,
@@ -32,6 +455,15 @@ pub struct IParametricSurface {
     pub wireframe_color: String,
     pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
     pub uv_lens: [f32; 2],
+    pub rotation_4d: [f32; 6], // angles in the xy, xz, xw, yz, yw and zw planes for the 4D surfaces
+    pub projection_4d: u32, // 0: stereographic, 1: perspective
+    pub distance_4d: f32, // distance of the perspective viewpoint on the w-axis
+    pub weld_seams: bool, // share the vertices of closed seams instead of duplicating them. off by default: the
+                          // welded seam reuses the uvs of the first row or column, which smears textures
+    pub adaptive: u32, // 0: uniform grid, 1: refine by chordal deviation, 2: refine by normal angle
+    pub tolerance: f32, // chordal deviation relative to the surface size for adaptive = 1
+    pub angle_tolerance: f32, // normal angle in radians for adaptive = 2
+    pub max_depth: u32, // quadtree levels below the u_resolution x v_resolution base grid
 }
 
 fn surface_type_map() -> HashMap<u32,String> {
@@ -59,6 +491,16 @@ fn surface_type_map() -> HashMap<u32,String> {
     surface_type.insert(20, String::from("steiner"));
     surface_type.insert(21, String::from("torus"));
     surface_type.insert(22, String::from("wellenkugel"));
+    surface_type.insert(23, String::from("mobius_strip"));
+    surface_type.insert(24, String::from("dini"));
+    surface_type.insert(25, String::from("catenoid"));
+    surface_type.insert(26, String::from("helicoid"));
+    surface_type.insert(27, String::from("costa"));
+    surface_type.insert(28, String::from("cross_cap"));
+    surface_type.insert(29, String::from("roman"));
+    surface_type.insert(30, String::from("clifford_torus"));
+    surface_type.insert(31, String::from("flat_torus"));
+    surface_type.insert(32, String::from("hopf_torus"));
     surface_type
 }
 
@@ -84,6 +526,14 @@ impl Default for IParametricSurface {
             wireframe_color: "white".to_string(),
             colormap_direction: 1,
             uv_lens: [1.0, 1.0],
+            rotation_4d: [0.0; 6],
+            projection_4d: 1,
+            distance_4d: 3.0,
+            weld_seams: false,
+            adaptive: 0,
+            tolerance: 0.002,
+            angle_tolerance: 0.1,
+            max_depth: 4,
         }
     }
 }
@@ -99,205 +549,204 @@ impl Default for IParametricSurface {
 
 impl IParametricSurface {
     pub fn new(&mut self) -> ISurfaceOutput {
+        let mut out = ISurfaceOutput::default();
+        self.new_into(&mut out);
+        out
+    }
+
+    // new written into an existing output, whose buffers are reused when the surface is regenerated
+    // every frame
+    pub fn new_into(&mut self, out:&mut ISurfaceOutput) {
         if self.surface_type == 1 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::astroid)
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, -0.5*PI, 0.5*PI);
+            self.parametric_surface_data(&mf::astroid, out)
         } else if self.surface_type == 2 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::astroid2)
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, PI, 0.0, 2.0*PI);
+            self.parametric_surface_data(&mf::astroid2, out)
         } else if self.surface_type == 3 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-PI, PI, 0.0, 5.0);
-            self.parametric_surface_data(&mf::astroidal_torus)
+            self.parametric_surface_data(&mf::astroidal_torus, out)
         } else if self.surface_type == 4 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::bohemian_dome)
+            self.parametric_surface_data(&mf::bohemian_dome, out)
         } else if self.surface_type == 5 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, PI, 0.0, PI);
-            self.parametric_surface_data(&mf::boy_shape)
+            self.parametric_surface_data(&mf::boy_shape, out)
         } else if self.surface_type == 6 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-14.0, 14.0, -12.0*PI, 12.0*PI);
-            self.parametric_surface_data(&mf::breather)
+            self.parametric_surface_data(&mf::breather, out)
         } else if self.surface_type == 7 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-3.3, 3.3, -3.3, 3.3);
-            self.parametric_surface_data(&mf::enneper)
+            self.parametric_surface_data(&mf::enneper, out)
         } else if self.surface_type == 8 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 4.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::figure8)
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
+            self.parametric_surface_data(&mf::figure8, out)
         } else if self.surface_type == 9 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.0, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::henneberg)
+            self.parametric_surface_data(&mf::henneberg, out)
         } else if self.surface_type == 10 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-0.99999, 0.99999, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::kiss)
+            self.parametric_surface_data(&mf::kiss, out)
         } else if self.surface_type == 11 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::klein_bottle2)
+            self.parametric_surface_data(&mf::klein_bottle2, out)
         } else if self.surface_type == 12 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 4.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::klein_bottle3)
+            self.parametric_surface_data(&mf::klein_bottle3, out)
         } else if self.surface_type == 13 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-4.5, 4.5, -5.0, 5.0);
-            self.parametric_surface_data(&mf::kuen)
+            self.parametric_surface_data(&mf::kuen, out)
         } else if self.surface_type == 14 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-3.0, 1.0, -3.0*PI, 3.0*PI);
-            self.parametric_surface_data(&mf::minimal)
+            self.parametric_surface_data(&mf::minimal, out)
         } else if self.surface_type == 15 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-5.0, 5.0, -5.0, 5.0);
-            self.parametric_surface_data(&mf::parabolic_cyclide)
+            self.parametric_surface_data(&mf::parabolic_cyclide, out)
         } else if self.surface_type == 16 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.0, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::pear)
+            self.parametric_surface_data(&mf::pear, out)
         } else if self.surface_type == 17 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-2.0, 2.0, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::plucker_conoid)
+            self.parametric_surface_data(&mf::plucker_conoid, out)
         } else if self.surface_type == 18 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 6.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::seashell)
+            self.parametric_surface_data(&mf::seashell, out)
         } else if self.surface_type == 19 {
             (self.umin, self.umax, self.vmin, self.vmax) = (-PI/2.1, PI/2.1, 0.001, PI/1.001);
-            self.parametric_surface_data(&mf::sievert_enneper)
+            self.parametric_surface_data(&mf::sievert_enneper, out)
         } else if self.surface_type == 20 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.999999*PI, 0.0, 0.999999*PI);
-            self.parametric_surface_data(&mf::steiner)
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 0.5*PI);
+            self.parametric_surface_data(&mf::steiner, out)
         } else if self.surface_type == 21 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::torus)
+            self.parametric_surface_data(&mf::torus, out)
         } else if self.surface_type == 22 {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 14.5, 0.0, 5.2);
-            self.parametric_surface_data(&mf::wellenkugel)
+            self.parametric_surface_data(&mf::wellenkugel, out)
+        } else if self.surface_type == 23 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, -0.4, 0.4);
+            self.parametric_surface_data(&mf::mobius_strip, out)
+        } else if self.surface_type == 24 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 4.0*PI, 0.05, 2.0);
+            self.parametric_surface_data(&mf::dini, out)
+        } else if self.surface_type == 25 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, -1.5, 1.5);
+            self.parametric_surface_data(&mf::catenoid, out)
+        } else if self.surface_type == 26 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (-PI, PI, -2.0, 2.0);
+            self.parametric_surface_data(&mf::helicoid, out)
+        } else if self.surface_type == 27 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.05, 0.95, 0.05, 0.95);
+            self.parametric_surface_data(&mf::costa, out)
+        } else if self.surface_type == 28 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.001, 0.5*PI);
+            self.parametric_surface_data(&mf::cross_cap, out)
+        } else if self.surface_type == 29 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.001, 0.999*PI);
+            self.parametric_surface_data(&mf::roman, out)
+        } else if self.surface_type == 30 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
+            self.parametric_surface_data_4d(&mf::clifford_torus, out)
+        } else if self.surface_type == 31 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
+            self.parametric_surface_data_4d(&mf::flat_torus, out)
+        } else if self.surface_type == 32 {
+            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0*PI, 0.0, 2.0*PI);
+            self.parametric_surface_data_4d(&mf::hopf_torus, out)
         } else {
             (self.umin, self.umax, self.vmin, self.vmax) = (0.0, PI, 0.0, 2.0*PI);
-            self.parametric_surface_data(&mf::klein_bottle)
+            self.parametric_surface_data(&mf::klein_bottle, out)
         }
     }
 
-    fn parametric_surface_data(&mut self, f:&dyn Fn(f32, f32) -> [f32; 3]) -> ISurfaceOutput {
-        let mut positions: Vec<[f32; 3]> = vec![];
-        let mut normals: Vec<[f32; 3]> = vec![];
-        let mut colors: Vec<[f32; 3]> = vec![];
-        let mut colors2: Vec<[f32; 3]> = vec![];
-        let mut uvs: Vec<[f32; 2]> = vec![];
+    // 4D surface rotated by rotation_4d and projected into 3D before meshing
+    fn parametric_surface_data_4d(&mut self, f:&(dyn Fn(f32, f32) -> [f32; 4] + Sync), out:&mut ISurfaceOutput) {
+        let (angles, projection, distance) = (self.rotation_4d, self.projection_4d, self.distance_4d);
+        self.parametric_surface_data(&|u, v| mf::project_4d(mf::rotate_4d(f(u, v), angles), projection, distance), out)
+    }
 
+    // the rows of the grid are sampled in parallel into the flat buffers of out
+    fn parametric_surface_data(&mut self, f:&(dyn Fn(f32, f32) -> [f32; 3] + Sync), out:&mut ISurfaceOutput) {
+        if self.adaptive > 0 {
+            *out = self.adaptive_surface_data(f);
+            out.dirty = IDirtyBuffers::all();
+            return;
+        }
+        let (umin, vmin) = (self.umin, self.vmin);
         let du = (self.umax - self.umin)/self.u_resolution as f32;
         let dv = (self.vmax - self.vmin)/self.v_resolution as f32;
         let (epsu, epsv) = (0.01 * du, 0.01 * dv);
-        //let (mut p0, mut p1, mut p2, mut p3): (Vector3<f32>, Vector3<f32>, Vector3<f32>, Vector3<f32>);
-
-        let (min_val, max_val, pts) = self.parametric_surface_range(f);
-        let cdata = colormap::colormap_data(&self.colormap_name);
-        let cdata2 = colormap::colormap_data(&self.wireframe_color);
-
-        for i in 0..=self.u_resolution {
-            let u = self.umin + du * i as f32;
-            for j in 0..=self.v_resolution {
-                let v = self.vmin + dv * j as f32;                
-                positions.push(pts[i as usize][j as usize]);
-
-                // calculate normals
-                /*p0 = Vector3::from(f(u, v));
-                if u - epsu >= 0.0 {
-                    p1 = Vector3::from(f(u-epsu, v));
-                    p2 = p0 - p1;
-                } else {
-                    p1 = Vector3::from(f(u+epsu, v));
-                    p2 = p1 - p0;
-                }
-                if v - epsv >= 0.0 {
-                    p1 = Vector3::from(f(u, v-epsv));
-                    p3 = p0 - p1;
-                } else {
-                    p1 = Vector3::from(f(u, v+epsv));
-                    p3 = p1 - p0;
-                }
-                let normal = p2.cross(p3).normalize();*/
+        let row = self.v_resolution as usize + 1;
+        out.reset_vertices((self.u_resolution as usize + 1) * row);
 
+        // positions, and normals and tangents from central differences
+        out.positions.par_chunks_mut(row).zip(out.normals.par_chunks_mut(row)).zip(out.tangents.par_chunks_mut(row))
+            .enumerate().for_each(|(i, ((positions, normals), tangents))| {
+            let u = umin + du * i as f32;
+            for j in 0..row {
+                let v = vmin + dv * j as f32;
+                positions[j] = f(u, v);
                 let nu = Vector3::from(f(u+epsu, v)) - Vector3::from(f(u-epsu, v));
                 let nv = Vector3::from(f(u, v+epsv)) - Vector3::from(f(u, v-epsv));
                 let normal = nu.cross(nv).normalize();
-                normals.push(normal.into());
+                normals[j] = normal.into();
+                tangents[j] = tangent_frame(nu, nv, normal);
+            }
+        });
 
-                // colormap
-                let color = colormap::color_lerp(cdata, min_val, max_val, 
-                    pts[i as usize][j as usize][self.colormap_direction as usize]);
-                let color2 = colormap::color_lerp(cdata2, min_val, max_val, 
-                    pts[i as usize][j as usize][self.colormap_direction as usize]);
-                colors.push(color);
-                colors2.push(color2);
+        let (min_val, max_val) = self.parametric_surface_range(&mut out.positions);
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let dir = self.colormap_direction as usize;
+        out.colors.par_iter_mut().zip(out.colors2.par_iter_mut()).zip(out.positions.par_iter())
+            .for_each(|((color, color2), p)| {
+            *color = colormap::color_lerp(cdata, min_val, max_val, p[dir]);
+            *color2 = colormap::color_lerp(cdata2, min_val, max_val, p[dir]);
+        });
 
-                // uvs
-                uvs.push([self.uv_lens[0]*(u-self.umin)/(self.umax-self.umin), 
-                    self.uv_lens[1]*(v-self.vmin)/(self.vmax-self.vmin)
-                ]);
+        let (uv_lens, nu, nv) = (self.uv_lens, self.u_resolution as f32, self.v_resolution as f32);
+        out.uvs.par_chunks_mut(row).enumerate().for_each(|(i, uvs)| {
+            for (j, uv) in uvs.iter_mut().enumerate() {
+                *uv = [uv_lens[0] * i as f32 / nu, uv_lens[1] * j as f32 / nv];
             }
-        }
+        });
 
         // calculate indices
-        let mut indices: Vec<u16> = vec![];
-        let mut indices2: Vec<u16> = vec![];
-        let vertices_per_row = self.v_resolution + 1;
-
-        for i in 0..self.u_resolution {
-            for j in 0..self.v_resolution {
-                let idx0 = j + i * vertices_per_row;
-                let idx1 = j + 1 + i * vertices_per_row;
-                let idx2 = j + 1 + (i + 1) * vertices_per_row;
-                let idx3 = j + (i + 1) * vertices_per_row; 
-
-                let values:Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
-                indices.extend(values);
-
-                let values2:Vec<u16> = vec![idx0, idx1, idx0, idx3];
-                indices2.extend(values2);
-                if i == self.u_resolution - 1 || j == self.v_resolution - 1 {
-                    let edge_values:Vec<u16> = vec![idx1, idx2, idx2, idx3];
-                    indices2.extend(edge_values);
-                }
-            }
-        }
-
-        ISurfaceOutput { positions, normals, colors, colors2, uvs, indices, indices2 }
+        let seams = if self.weld_seams { get_surface_periodicity(self.surface_type) } else { [0, 0] };
+        grid_indices_into(self.u_resolution as u32, self.v_resolution as u32, seams, &mut out.indices, &mut out.indices2);
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.dirty = IDirtyBuffers::all();
     }
 
-    fn parametric_surface_range(&mut self, f:&dyn Fn(f32, f32) -> [f32; 3]) -> (f32, f32, Vec<Vec<[f32;3]>>) {
-        let du = (self.umax - self.umin)/self.u_resolution as f32;
-        let dv = (self.vmax - self.vmin)/self.v_resolution as f32;
-        let (mut xmin, mut ymin, mut zmin) = (f32::MAX, f32::MAX, f32::MAX);
-        let (mut xmax, mut ymax, mut zmax) = (f32::MIN, f32::MIN, f32::MIN);       
-
-        let mut pts: Vec<Vec<[f32; 3]>> = vec![];
-        for i in 0..=self.u_resolution {
-            let u = self.umin + du * i as f32;
-            let mut pt1: Vec<[f32; 3]> = vec![];
-            for j in 0..=self.v_resolution {
-                let v = self.vmin + dv * j as f32;
-                let pt = f(u, v);
-                xmin = if pt[0] < xmin { pt[0] } else { xmin };
-                xmax = if pt[0] > xmax { pt[0] } else { xmax };
-                ymin = if pt[1] < ymin { pt[1] } else { ymin };
-                ymax = if pt[1] > ymax { pt[1] } else { ymax };
-                zmin = if pt[2] < zmin { pt[2] } else { zmin };
-                zmax = if pt[2] > zmax { pt[2] } else { zmax };
-                pt1.push(pt);
+    // center the positions and fit them into [-scale, scale], and return the range of the colormap
+    // coordinate. non-finite samples are left out of the range and cut from the mesh later.
+    fn parametric_surface_range(&mut self, positions:&mut [[f32; 3]]) -> (f32, f32) {
+        let (pmin, pmax) = positions.par_iter()
+            .filter(|p| p.iter().all(|c| c.is_finite()))
+            .fold(|| ([f32::MAX; 3], [f32::MIN; 3]), |(mut pmin, mut pmax), p| {
+                for k in 0..3 {
+                    pmin[k] = pmin[k].min(p[k]);
+                    pmax[k] = pmax[k].max(p[k]);
+                }
+                (pmin, pmax)
+            })
+            .reduce(|| ([f32::MAX; 3], [f32::MIN; 3]), |a, b| {
+                ([a.0[0].min(b.0[0]), a.0[1].min(b.0[1]), a.0[2].min(b.0[2])],
+                 [a.1[0].max(b.1[0]), a.1[1].max(b.1[1]), a.1[2].max(b.1[2])])
+            });
+        let center = [0.5 * (pmin[0] + pmax[0]), 0.5 * (pmin[1] + pmax[1]), 0.5 * (pmin[2] + pmax[2])];
+        let dist = (pmax[0] - pmin[0]).max(pmax[1] - pmin[1]).max(pmax[2] - pmin[2]);
+        let scale = self.scale;
+        positions.par_iter_mut().for_each(|p| {
+            for k in 0..3 {
+                p[k] = scale * (p[k] - center[k]) / dist;
             }
-            pts.push(pt1);
-        }
+        });
 
-        let (mut min_val, mut max_val) = (f32::MAX, f32::MIN);
-        let dist = (xmax - xmin).max(ymax - ymin).max(zmax - zmin);
-
-        for i in 0..=self.u_resolution {
-            for j in 0..=self.v_resolution {
-                let mut pt = pts[i as usize][j as usize];
-                pt[0] = self.scale * (pt[0] - 0.5 * (xmin + xmax)) / dist;
-                pt[1] = self.scale * (pt[1] - 0.5 * (ymin + ymax)) / dist;
-                pt[2] = self.scale * (pt[2] - 0.5 * (zmin + zmax)) / dist;
-                let pt1 = pt[self.colormap_direction as usize];
-                min_val = if pt1 < min_val { pt1 } else { min_val };
-                max_val = if pt1 > max_val { pt1 } else { max_val };
-                pts[i as usize][j as usize] = pt;
-            }
-        }
-        (min_val, max_val, pts)
+        let dir = self.colormap_direction as usize;
+        let (a, b) = (scale * (pmin[dir] - center[dir]) / dist, scale * (pmax[dir] - center[dir]) / dist);
+        (a.min(b), a.max(b))
     }
 }
 // endregion: parametric surface
@@ -318,6 +767,8 @@ pub struct ISimpleSurface {
     pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
     pub t: f32,  // animation time parameter
     pub uv_lens: [f32; 2],
+    pub normalization: u32, // 0: y-range of each frame, 1: fixed y_range for the height and the colormap
+    pub y_range: [f32; 2], // [ymin, ymax] mapped onto [-aspect_ratio, aspect_ratio] when normalization is 1
 }
 
 impl Default for ISimpleSurface {
@@ -337,60 +788,383 @@ impl Default for ISimpleSurface {
             colormap_direction: 1,
             t: 0.0,
             uv_lens: [1.0, 1.0],
+            normalization: 0,
+            y_range: [-1.0, 1.0],
         }
     }
 }
 
 impl ISimpleSurface { 
     pub fn new(&mut self) -> ISurfaceOutput {
+        let mut out = ISurfaceOutput::default();
+        self.new_into(&mut out);
+        out
+    }
+
+    // new written into an existing output, whose buffers are reused when the surface is animated
+    pub fn new_into(&mut self, out:&mut ISurfaceOutput) {
+        let f = self.preset_function();
+        self.simple_surface_data(&f, out);
+    }
+
+    // recompute the positions, normals and tangents of an output of new_into at time t. the colors
+    // are recomputed when they follow the y-direction or when holes are open before or after the
+    // update, since cut_holes zeroes the colors of the vertices in a hole. the indices are only
+    // rebuilt when holes open or close. returns the buffers that changed. only the built-in presets
+    // of surface_type are updated: an output of height_grid_data or complex_surface_data, or of an
+    // IScatterSurface, is overwritten with the preset, so those are built again with their own
+    // functions, which mark every buffer dirty.
+    pub fn update_time(&mut self, t:f32, out:&mut ISurfaceOutput) -> IDirtyBuffers {
+        self.t = t;
+        let f = self.preset_function();
+        self.update_surface_data(&f, out)
+    }
+
+    // recompute only the colors of an output of new_into after a change of colormap_name,
+    // wireframe_color or colormap_direction. returns the buffers that changed.
+    pub fn update_colormap(&mut self, out:&mut ISurfaceOutput) -> IDirtyBuffers {
+        self.simple_colors(out);
+        let dirty = IDirtyBuffers { colors: true, colors2: true, ..Default::default() };
+        out.dirty.merge(dirty);
+        dirty
+    }
+
+    // fix the y-range to the one of the animation over t in [tmin, tmax], sampled at the given number
+    // of frames, so that the height scale and the colors stay put from frame to frame
+    pub fn set_time_window(&mut self, tmin:f32, tmax:f32, samples:u32) {
+        let f = self.preset_function();
+        self.y_range = self.time_window_range(&f, tmin, tmax, samples);
+        self.normalization = 1;
+    }
+
+    // y-range of f over the x-z domain and over t in [tmin, tmax]
+    pub fn time_window_range(&self, f:&(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), tmin:f32, tmax:f32, samples:u32) -> [f32; 2] {
+        let n = samples.max(1);
+        let (mut ymin, mut ymax) = (f32::MAX, f32::MIN);
+        for k in 0..n {
+            let t = if n == 1 { tmin } else { tmin + (tmax - tmin) * k as f32 / (n - 1) as f32 };
+            let (a, b) = self.frame_yrange(f, t);
+            (ymin, ymax) = (ymin.min(a), ymax.max(b));
+        }
+        [ymin, ymax]
+    }
+
+    // set the domain and aspect ratio of surface_type and return its function
+    fn preset_function(&mut self) -> fn(f32, f32, f32) -> [f32; 3] {
         if self.surface_type == 0 {
             (self.xmin, self.xmax, self.zmin, self.zmax) = (-8.0, 8.0, -8.0, 8.0);
             self.aspect_ratio = 0.5;
-            self.simple_surface_data(&mf::sinc)
+            mf::sinc
         } else if self.surface_type == 1 {
             (self.xmin, self.xmax, self.zmin, self.zmax) = (-8.0, 8.0, -8.0, 8.0);
             self.aspect_ratio = 0.6;
-            self.simple_surface_data(&mf::poles)
+            mf::poles
+        } else if self.surface_type == 3 {
+            (self.xmin, self.xmax, self.zmin, self.zmax) = (-1.0, 1.0, -1.0, 1.0);
+            self.aspect_ratio = 0.8;
+            mf::monkey_saddle
+        } else if self.surface_type == 4 {
+            (self.xmin, self.xmax, self.zmin, self.zmax) = (-2.0, 2.0, -1.0, 3.0);
+            self.aspect_ratio = 0.6;
+            mf::rosenbrock
+        } else if self.surface_type == 5 {
+            (self.xmin, self.xmax, self.zmin, self.zmax) = (-5.0, 5.0, -5.0, 5.0);
+            self.aspect_ratio = 0.6;
+            mf::himmelblau
+        } else if self.surface_type == 6 {
+            (self.xmin, self.xmax, self.zmin, self.zmax) = (-5.0, 5.0, -5.0, 5.0);
+            self.aspect_ratio = 0.5;
+            mf::ackley
+        } else if self.surface_type == 7 {
+            (self.xmin, self.xmax, self.zmin, self.zmax) = (-10.0, 10.0, -10.0, 10.0);
+            self.aspect_ratio = 0.3;
+            mf::ripple
         } else {
             (self.xmin, self.xmax, self.zmin, self.zmax) = (-3.0, 3.0, -3.0, 3.0);
             self.aspect_ratio = 0.9;
-            self.simple_surface_data(&mf::peaks)  
+            mf::peaks
         }
     }
 
-    fn simple_surface_data(&mut self, f:&dyn Fn(f32, f32, f32) -> [f32; 3]) -> ISurfaceOutput {
-        let mut positions: Vec<[f32; 3]> = vec![];
-        let mut normals: Vec<[f32; 3]> = vec![];
-        let mut colors: Vec<[f32; 3]> = vec![];
-        let mut colors2: Vec<[f32; 3]> = vec![];
-        let mut uvs: Vec<[f32; 2]> = vec![];
-        
+    // simple surface resampled from a height grid at x_resolution x z_resolution. filter 0: bilinear,
+    // 1: bicubic. exaggeration 1.0 keeps the true height to width ratio of the grid.
+    pub fn height_grid_data(&mut self, grid:&IHeightGrid, filter:u32, exaggeration:f32) -> anyhow::Result<ISurfaceOutput> {
+        let (hmin, hmax) = grid.height_range()?;
+        (self.xmin, self.xmax, self.zmin, self.zmax) = (grid.xmin, grid.xmax, grid.zmin, grid.zmax);
+        let width = (grid.xmax - grid.xmin).max(grid.zmax - grid.zmin);
+        self.aspect_ratio = exaggeration * (hmax - hmin) / width;
+        // cells without data sample as NaN and become holes
+        let mut out = ISurfaceOutput::default();
+        self.simple_surface_data(&|x, z, _t| [x, grid.sample(x, z, filter), z], &mut out);
+        Ok(out)
+    }
+
+    // domain coloring of a complex function w = f(z, t) with z = x + iz over the x-z domain.
+    // height_type 0: |w|, 1: log|w|. the modulus is clamped to [1/max_modulus, max_modulus] so poles
+    // and zeros don't flatten the rest of the surface. the color is arg(w) through the cyclic "hsv"
+    // colormap, darkened in bands between the contours |w| = 2^k when contours is true.
+    pub fn complex_surface_data(&mut self, f:&(dyn Fn(Complex32, f32) -> Complex32 + Sync), height_type:u32,
+    max_modulus:f32, contours:bool) -> ISurfaceOutput {
+        // a sample right on a pole divides by zero and comes out as NaN
+        let modulus = |w:Complex32| if w.norm().is_nan() { max_modulus } else { w.norm().clamp(1.0 / max_modulus, max_modulus) };
+        let height = |w:Complex32| if height_type == 1 { modulus(w).ln() } else { modulus(w) };
+        let mut output = ISurfaceOutput::default();
+        self.simple_surface_data(&|x, z, t| [x, height(f(Complex32::new(x, z), t)), z], &mut output);
+
+        let cdata = colormap::colormap_data("hsv");
+        let dx = (self.xmax - self.xmin) / self.x_resolution as f32;
+        let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
+        output.colors.clear();
+        for i in 0..=self.x_resolution {
+            let x = self.xmin + dx * i as f32;
+            for j in 0..=self.z_resolution {
+                let z = self.zmin + dz * j as f32;
+                let w = f(Complex32::new(x, z), self.t);
+                let arg = if w.arg().is_nan() { 0.0 } else { w.arg() };
+                let mut color = colormap::color_lerp(cdata, -PI, PI, arg);
+                if contours {
+                    let band = modulus(w).log2();
+                    let shade = 0.7 + 0.3 * (band - band.floor());
+                    color = [shade * color[0], shade * color[1], shade * color[2]];
+                }
+                output.colors.push(color);
+            }
+        }
+        output
+    }
+
+    // update_time for any f, leaving the output as simple_surface_data would have written it
+    fn update_surface_data(&mut self, f:&(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), out:&mut ISurfaceOutput) -> IDirtyBuffers {
+        if out.positions.len() != (self.x_resolution as usize + 1) * (self.z_resolution as usize + 1) {
+            self.simple_surface_data(f, out);
+            return out.dirty;
+        }
+        let mut dirty = IDirtyBuffers { positions: true, normals: true, tangents: true, ..Default::default() };
+        let had_holes = out.rejected > 0;
+        self.simple_vertices(f, out);
+        let has_holes = out.positions.par_iter().any(|p| !p.iter().all(|c| c.is_finite()));
+        if self.colormap_direction == 1 || had_holes || has_holes {
+            self.simple_colors(out);
+            (dirty.colors, dirty.colors2) = (true, true);
+        }
+        if had_holes {
+            grid_indices_into(self.x_resolution as u32, self.z_resolution as u32, [0, 0], &mut out.indices, &mut out.indices2);
+            (dirty.indices, dirty.indices2) = (true, true);
+        }
+        out.reject_non_finite();
+        if out.rejected > 0 {
+            (dirty.indices, dirty.indices2) = (true, true);
+        }
+        out.dirty.merge(dirty);
+        dirty
+    }
+
+    // the rows of the grid are sampled in parallel into the flat buffers of out
+    fn simple_surface_data(&mut self, f:&(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), out:&mut ISurfaceOutput) {
+        out.reset_vertices((self.x_resolution as usize + 1) * (self.z_resolution as usize + 1));
+        self.simple_vertices(f, out);
+        self.simple_colors(out);
+
+        // uvs
+        let row = self.z_resolution as usize + 1;
+        let (uv_lens, res_x, res_z) = (self.uv_lens, self.x_resolution as f32, self.z_resolution as f32);
+        out.uvs.par_chunks_mut(row).enumerate().for_each(|(i, uvs)| {
+            for (j, uv) in uvs.iter_mut().enumerate() {
+                *uv = [uv_lens[0] * i as f32 / res_x, uv_lens[1] * j as f32 / res_z];
+            }
+        });
+
+        // calculate indices
+        grid_indices_into(self.x_resolution as u32, self.z_resolution as u32, [0, 0], &mut out.indices, &mut out.indices2);
+        out.reject_non_finite();
+        out.dirty = IDirtyBuffers::all();
+    }
+
+    // positions, and normals and tangents from central differences, at time t. f is evaluated once per
+    // vertex for the raw position and four times for the differences, which the normals and tangents
+    // hold until the y-range of the raw positions sets the normalization that scales them.
+    fn simple_vertices(&self, f:&(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), out:&mut ISurfaceOutput) {
         let dx = (self.xmax- self.xmin) / self.x_resolution as f32;
         let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
         let (epsx, epsz) = (0.01 * dx, 0.01 * dz);
-        
-        let (ymin, ymax) = self.yrange(f);
+        let row = self.z_resolution as usize + 1;
+        let t = self.t;
+
+        out.positions.par_chunks_mut(row).zip(out.normals.par_chunks_mut(row)).zip(out.tangents.par_chunks_mut(row))
+            .enumerate().for_each(|(i, ((positions, normals), tangents))| {
+            let x = self.xmin + dx * i as f32;
+            for j in 0..row {
+                let z = self.zmin + dz * j as f32;
+                positions[j] = f(x, z, t);
+                let nx = Vector3::from(f(x+epsx, z, t)) - Vector3::from(f(x-epsx, z, t));
+                let nz = Vector3::from(f(x, z+epsz, t)) - Vector3::from(f(x, z-epsz, t));
+                normals[j] = nx.into();
+                tangents[j] = [nz.x, nz.y, nz.z, 0.0];
+            }
+        });
+
+        let (ymin, ymax) = if self.normalization == 1 && self.y_range[1] > self.y_range[0] {
+            (self.y_range[0], self.y_range[1])
+        } else {
+            out.positions.par_iter().filter(|p| p[1].is_finite()).map(|p| (p[1], p[1]))
+                .reduce(|| (f32::MAX, f32::MIN), |a, b| (a.0.min(b.0), a.1.max(b.1)))
+        };
+
+        // normalize_data scales each axis, and the differences with it
+        let k = Vector3::new(2.0 * self.scale / (self.xmax - self.xmin), 2.0 * self.scale * self.aspect_ratio / (ymax - ymin),
+            2.0 * self.scale / (self.zmax - self.zmin));
+        out.positions.par_iter_mut().zip(out.normals.par_iter_mut()).zip(out.tangents.par_iter_mut())
+            .for_each(|((position, normal), tangent)| {
+            *position = self.normalize_data(*position, ymin, ymax);
+            let nx = Vector3::from(*normal).mul_element_wise(k);
+            let nz = Vector3::new(tangent[0], tangent[1], tangent[2]).mul_element_wise(k);
+            let n = nx.cross(nz).normalize();
+            *normal = n.into();
+            *tangent = tangent_frame(nx, nz, n);
+        });
+    }
+
+    fn simple_colors(&self, out:&mut ISurfaceOutput) {
         let cdata = colormap::colormap_data(&self.colormap_name);
         let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let range = if self.colormap_direction == 1 { self.scale * self.aspect_ratio } else { self.scale };
+        let dir = self.colormap_direction as usize;
+        out.colors.resize(out.positions.len(), [0.0; 3]);
+        out.colors2.resize(out.positions.len(), [0.0; 3]);
+        out.colors.par_iter_mut().zip(out.colors2.par_iter_mut()).zip(out.positions.par_iter())
+            .for_each(|((color, color2), p)| {
+            *color = colormap::color_lerp(cdata, -range, range, p[dir]);
+            *color2 = colormap::color_lerp(cdata2, -range, range, p[dir]);
+        });
+    }
 
-        for i in 0..=self.x_resolution {
+    fn normalize_data(&self, point:[f32; 3], ymin:f32, ymax:f32) -> [f32; 3] {
+        let mut pt = point;
+        pt[0] = (-1.0 + 2.0 * (pt[0] - self.xmin) / (self.xmax - self.xmin)) * self.scale;
+        pt[1] = (-1.0 + 2.0 * (pt[1] - ymin) / (ymax - ymin)) * self.scale * self.aspect_ratio;
+        pt[2] = (-1.0 + 2.0 * (pt[2] - self.zmin) / (self.zmax - self.zmin)) * self.scale;
+        pt
+    }
+
+    fn frame_yrange(&self, f:&(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), t:f32) -> (f32, f32) {
+        let dx = (self.xmax- self.xmin) / self.x_resolution as f32;
+        let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
+        (0..=self.x_resolution).into_par_iter().map(|i| {
             let x = self.xmin + dx * i as f32;
+            let (mut ymin, mut ymax) = (f32::MAX, f32::MIN);
             for j in 0..=self.z_resolution {
                 let z = self.zmin + dz * j as f32;
-                let pos = self.normalize_data(f(x,z,self.t), ymin, ymax);
+                let pt = f(x, z, t);
+                if pt[1].is_finite() {
+                    ymin = if pt[1] < ymin { pt[1] } else { ymin };
+                    ymax = if pt[1] > ymax { pt[1] } else { ymax };
+                }
+            }
+            (ymin, ymax)
+        }).reduce(|| (f32::MAX, f32::MIN), |a, b| (a.0.min(b.0), a.1.max(b.1)))
+    }
+}
+// endregion: simple surface
+
+// region: super shape
+pub struct ISuperShape {
+    pub n1: [f32; 4],
+    pub n2: [f32; 4],
+    pub a1: [f32; 2],
+    pub a2: [f32; 2],
+    pub u_resolution: u16,
+    pub v_resolution: u16,
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub t: f32,  // animation time parameter
+    pub uv_lens: [f32; 2],
+    pub weld_seams: bool, // share the vertices of closed seams, off by default as in IParametricSurface
+}
+
+// the presets of the ch04 super_shape example: (n1, n2, scale)
+pub fn get_super_shape_preset(key:u32) -> ([f32; 4], [f32; 4], f32) {
+    match key {
+        1 => ([0.0, 1.0, 1.0, 1.0], [0.0, 1.0, 1.0, 1.0], 1.0),
+        2 => ([4.0, 1.0, 1.0, 1.0], [4.0, 1.0, 1.0, 1.0], 1.0),
+        3 => ([6.0, 1.0, 1.0, 1.0], [3.0, 1.0, 1.0, 1.0], 1.0),
+        4 => ([5.0, 0.1, 1.7, 1.7], [1.0, 0.3, 0.5, 0.5], 1.0),
+        5 => ([6.0, 0.25, 47.6, -0.66], [7.0, -77.0, 0.52, -56.7], 0.5),
+        6 => ([0.0, 0.48, 30.25, 0.35], [5.0, 15.4, -0.45, 97.0], 1.0),
+        7 => ([2.0, 1.0, 97.7, -0.44], [7.0, -8.1, -0.09, 93.0], 1.0),
+        8 => ([6.0, 0.77, 52.3, -0.2], [5.0, 37.7, 0.53, -19.0], 1.0),
+        _ => ([7.0, 0.2, 1.7, 1.7], [7.0, 0.2, 1.7, 1.7], 1.0),
+    }
+}
+
+impl Default for ISuperShape {
+    fn default() -> Self {
+        let (n1, n2, scale) = get_super_shape_preset(0);
+        Self {
+            n1,
+            n2,
+            a1: [1.0, 1.0],
+            a2: [1.0, 1.0],
+            u_resolution: 64,
+            v_resolution: 64,
+            scale,
+            aspect_ratio: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            t: 0.0,
+            uv_lens: [1.0, 1.0],
+            weld_seams: false,
+        }
+    }
+}
+
+impl ISuperShape {
+    pub fn new(&mut self) -> ISurfaceOutput {
+        self.super_shape_data()
+    }
+
+    pub fn set_preset(&mut self, key:u32) {
+        (self.n1, self.n2, self.scale) = get_super_shape_preset(key);
+    }
+
+    // same domain, scaling, normals and colormap range as super_shape_comp.wgsl
+    fn super_shape_data(&mut self) -> ISurfaceOutput {
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut normals: Vec<[f32; 3]> = vec![];
+        let mut colors: Vec<[f32; 3]> = vec![];
+        let mut colors2: Vec<[f32; 3]> = vec![];
+        let mut uvs: Vec<[f32; 2]> = vec![];
+        let mut tangents: Vec<[f32; 4]> = vec![];
+
+        let (umin, umax, vmin, vmax) = (-PI, PI, -0.5*PI, 0.5*PI);
+        let du = (umax - umin)/self.u_resolution as f32;
+        let dv = (vmax - vmin)/self.v_resolution as f32;
+        let (epsu, epsv) = (0.01 * du, 0.01 * dv);
+
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let range = if self.colormap_direction == 1 { self.aspect_ratio } else { 1.0 };
+
+        for i in 0..=self.u_resolution {
+            let u = umin + du * i as f32;
+            for j in 0..=self.v_resolution {
+                let v = vmin + dv * j as f32;
+                let pos = self.normalize_point(u, v);
                 positions.push(pos);
 
                 // calculate normals
-                let nx = Vector3::from(self.normalize_data(f(x+epsx, z, self.t), ymin, ymax)) - 
-                         Vector3::from(self.normalize_data(f(x-epsx, z, self.t), ymin, ymax));
-                let nz = Vector3::from(self.normalize_data(f(x, z+epsz, self.t), ymin, ymax)) - 
-                         Vector3::from(self.normalize_data(f(x, z-epsz, self.t), ymin, ymax));
-                let normal = nx.cross(nz).normalize();
+                let nu = Vector3::from(self.normalize_point(u+epsu, v)) - 
+                         Vector3::from(self.normalize_point(u-epsu, v));
+                let nv = Vector3::from(self.normalize_point(u, v+epsv)) - 
+                         Vector3::from(self.normalize_point(u, v-epsv));
+                let normal = nu.cross(nv).normalize();
                 normals.push(normal.into());
+                tangents.push(tangent_frame(nu, nv, normal));
 
                 // colormap
-                let range = if self.colormap_direction == 1 { self.scale * self.aspect_ratio} 
-                    else {self.scale};
                 let color = colormap::color_lerp(cdata, -range, range, 
                     pos[self.colormap_direction as usize]);
                 let color2 = colormap::color_lerp(cdata2, -range, range, 
@@ -399,63 +1173,711 @@ impl ISimpleSurface {
                 colors2.push(color2);
 
                 // uvs
-                uvs.push([self.uv_lens[0]*(x-self.xmin)/(self.xmax-self.xmin), 
-                    self.uv_lens[1]*(z-self.zmin)/(self.zmax-self.zmin)
+                uvs.push([self.uv_lens[0]*(u-umin)/(umax-umin), 
+                    self.uv_lens[1]*(v-vmin)/(vmax-vmin)
                 ]);
             }
         }
 
         // calculate indices
-        let mut indices: Vec<u16> = vec![];
-        let mut indices2: Vec<u16> = vec![];
-        let vertices_per_row = self.z_resolution + 1;
-
-        for i in 0..self.x_resolution {
-            for j in 0..self.z_resolution {
-                let idx0 = j + i * vertices_per_row;
-                let idx1 = j + 1 + i * vertices_per_row;
-                let idx2 = j + 1 + (i + 1) * vertices_per_row;
-                let idx3 = j + (i + 1) * vertices_per_row; 
-
-                let values:Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
-                indices.extend(values);
-
-                let values2:Vec<u16> = vec![idx0, idx1, idx0, idx3];
-                indices2.extend(values2);
-                if i == self.x_resolution - 1 || j == self.z_resolution - 1 {
-                    let edge_values:Vec<u16> = vec![idx1, idx2, idx2, idx3];
-                    indices2.extend(edge_values);
+        let seams = [self.weld_seams as u32, 0];
+        let (indices, indices2) = periodic_grid_indices(self.u_resolution as u32, self.v_resolution as u32, seams);
+
+        let mut output = ISurfaceOutput { positions, normals, colors, colors2, uvs, indices, indices2, tangents, ..Default::default() };
+        output.reject_non_finite();
+        output.remove_unused_vertices();
+        output
+    }
+
+    fn normalize_point(&self, u:f32, v:f32) -> [f32; 3] {
+        let mut pt = mf::super_shape_3d(u, v, self.t, self.n1, self.n2, self.a1, self.a2);
+        pt[1] *= self.aspect_ratio;
+        [self.scale * pt[0], self.scale * pt[1], self.scale * pt[2]]
+    }
+}
+// endregion: super shape
+
+// region: surface of revolution and sweep surface
+// a 2D curve given either as a closure of s in [0, 1] or as a polyline
+pub enum IProfileCurve {
+    Func(Box<dyn Fn(f32) -> [f32; 2]>),
+    Polyline(Vec<[f32; 2]>),
+}
+
+impl IProfileCurve {
+    // closures are sampled at resolution + 1 points, polylines keep their own vertices
+    pub fn points(&self, resolution:u16) -> Vec<[f32; 2]> {
+        match self {
+            IProfileCurve::Func(f) => (0..=resolution).map(|i| f(i as f32/resolution as f32)).collect(),
+            IProfileCurve::Polyline(pts) => pts.clone(),
+        }
+    }
+}
+
+// a 3D curve given either as a closure of s in [0, 1] or as a polyline
+pub enum IPathCurve {
+    Func(Box<dyn Fn(f32) -> [f32; 3]>),
+    Polyline(Vec<[f32; 3]>),
+}
+
+impl IPathCurve {
+    pub fn points(&self, resolution:u16) -> Vec<[f32; 3]> {
+        match self {
+            IPathCurve::Func(f) => (0..=resolution).map(|i| f(i as f32/resolution as f32)).collect(),
+            IPathCurve::Polyline(pts) => pts.clone(),
+        }
+    }
+}
+
+// normalized cumulative arc length of a polyline, used for the uvs
+fn arc_length_params(pts:&[Vector3<f32>]) -> Vec<f32> {
+    let mut lens = vec![0.0f32];
+    for i in 1..pts.len() {
+        lens.push(lens[i-1] + (pts[i] - pts[i-1]).magnitude());
+    }
+    let total = lens[lens.len()-1];
+    if total > 0.0 {
+        lens.iter_mut().for_each(|l| *l /= total);
+    }
+    lens
+}
+
+// smooth normals of a row-by-row (nu + 1) x (nv + 1) vertex grid from central differences,
+// oriented like the triangles of grid_indices. closed directions wrap around the duplicated seam.
+pub(crate) fn grid_normals(pts:&[Vector3<f32>], nu:usize, nv:usize, u_closed:bool, v_closed:bool) -> Vec<[f32; 3]> {
+    let at = |i:usize, j:usize| pts[j + i * (nv + 1)];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    for i in 0..=nu {
+        let (i0, i1) = if u_closed && (i == 0 || i == nu) { (nu - 1, 1) } 
+            else { (i.saturating_sub(1), (i + 1).min(nu)) };
+        for j in 0..=nv {
+            let (j0, j1) = if v_closed && (j == 0 || j == nv) { (nv - 1, 1) } 
+                else { (j.saturating_sub(1), (j + 1).min(nv)) };
+            let du = at(i1, j) - at(i0, j);
+            let dv = at(i, j1) - at(i, j0);
+            let n = dv.cross(du);
+            normals.push(if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 0.0, 0.0] });
+        }
+    }
+    normals
+}
+
+// triangle fan closing a ring of grid vertices; the fan is wound so that its geometric normal is n
+pub(crate) fn add_cap(out:&mut ISurfaceOutput, ring:&[[f32; 3]], n:Vector3<f32>) {
+    let m = ring.len();
+    if m < 3 {
+        return;
+    }
+    let center = ring[..m - 1].iter().fold(Vector3::new(0.0, 0.0, 0.0), |c, p| c + Vector3::from(*p)) / (m - 1) as f32;
+    let i0 = out.positions.len() as u32;
+    out.positions.push(center.into());
+    out.normals.push(n.into());
+    out.uvs.push([0.5, 0.5]);
+    let (e1, e2) = (perpendicular(n), n.cross(perpendicular(n)));
+    for p in ring {
+        out.positions.push(*p);
+        out.normals.push(n.into());
+        let d = Vector3::from(*p) - center;
+        let a = d.dot(e2).atan2(d.dot(e1));
+        out.uvs.push([0.5 + 0.5 * a.cos(), 0.5 + 0.5 * a.sin()]);
+    }
+    for k in 0..m as u32 - 1 {
+        let (a, b) = (Vector3::from(ring[k as usize]) - center, Vector3::from(ring[k as usize + 1]) - center);
+        if a.cross(b).dot(n) >= 0.0 {
+            out.indices.extend([i0, i0 + 1 + k, i0 + 2 + k]);
+        } else {
+            out.indices.extend([i0, i0 + 2 + k, i0 + 1 + k]);
+        }
+    }
+}
+
+pub struct IRevolutionSurface {
+    pub profile: IProfileCurve, // [r, h]: distance from the axis and height along the axis
+    pub axis: [f32; 3],
+    pub angle: f32, // revolution angle in radians
+    pub u_resolution: u16, // samples along a closure profile
+    pub v_resolution: u16, // samples around the axis
+    pub caps: bool,
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub uv_lens: [f32; 2],
+    pub weld_seams: bool, // share the vertices of closed seams, off by default as in IParametricSurface
+}
+
+impl Default for IRevolutionSurface {
+    fn default() -> Self {
+        Self {
+            // the profile of mf::pear
+            profile: IProfileCurve::Func(Box::new(|s| [s * (s * (1.0 - s)).sqrt(), -s])),
+            axis: [0.0, 1.0, 0.0],
+            angle: 2.0 * PI,
+            u_resolution: 64,
+            v_resolution: 64,
+            caps: true,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            uv_lens: [1.0, 1.0],
+            weld_seams: false,
+        }
+    }
+}
+
+impl IRevolutionSurface {
+    // a profile of fewer than 2 points or no samples around the axis give an empty output
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let profile = self.profile.points(self.u_resolution);
+        if profile.len() < 2 || self.v_resolution == 0 {
+            return ISurfaceOutput::default();
+        }
+        let nu = profile.len() - 1;
+        let nv = self.v_resolution as usize;
+        let a = Vector3::from(self.axis).normalize();
+        let (e1, e2) = (perpendicular(a), a.cross(perpendicular(a)));
+        let closed = (self.angle.abs() - 2.0 * PI).abs() < 1e-4;
+
+        let pts2:Vec<Vector3<f32>> = profile.iter().map(|p| Vector3::new(p[0], p[1], 0.0)).collect();
+        let su = arc_length_params(&pts2);
+
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=nu {
+            // profile tangent (dr, dh)
+            let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nu));
+            let (dr, dh) = (profile[i1][0] - profile[i0][0], profile[i1][1] - profile[i0][1]);
+            for j in 0..=nv {
+                let phi = self.angle * j as f32 / nv as f32;
+                let radial = e1 * phi.cos() + e2 * phi.sin();
+                let pos = a * profile[i][1] + radial * profile[i][0];
+                out.positions.push(pos.into());
+                let n = radial * dh - a * dr;
+                out.normals.push(if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 0.0, 0.0] });
+                out.uvs.push([self.uv_lens[0] * su[i], self.uv_lens[1] * j as f32 / nv as f32]);
+            }
+        }
+        let seams = [0, (self.weld_seams && closed) as u32];
+        (out.indices, out.indices2) = periodic_grid_indices(nu as u32, nv as u32, seams);
+
+        if self.caps && closed {
+            let first = out.positions[0..=nv].to_vec();
+            let last = out.positions[nu * (nv + 1)..].to_vec();
+            let sign = if profile[nu][1] >= profile[0][1] { 1.0 } else { -1.0 };
+            if profile[0][0].abs() > 1e-6 {
+                add_cap(&mut out, &first, -a * sign);
+            }
+            if profile[nu][0].abs() > 1e-6 {
+                add_cap(&mut out, &last, a * sign);
+            }
+        }
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.compute_tangents();
+
+        out.normalize_positions(self.scale);
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        out
+    }
+}
+
+pub struct ISweepSurface {
+    pub path: IPathCurve,
+    pub cross_section: IProfileCurve, // [x, y] in the normal and binormal directions of the path frame
+    pub u_resolution: u16, // samples along a closure path
+    pub v_resolution: u16, // samples along a closure cross-section
+    pub caps: bool,
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub uv_lens: [f32; 2],
+    pub weld_seams: bool, // share the vertices of closed seams, off by default as in IParametricSurface
+}
+
+impl Default for ISweepSurface {
+    fn default() -> Self {
+        Self {
+            path: IPathCurve::Func(Box::new(|s| {
+                let t = 4.0 * PI * s;
+                [t.cos(), 0.2 * t, t.sin()]
+            })),
+            cross_section: IProfileCurve::Func(Box::new(|s| {
+                let t = 2.0 * PI * s;
+                [0.3 * t.cos(), 0.15 * t.sin()]
+            })),
+            u_resolution: 128,
+            v_resolution: 32,
+            caps: true,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            uv_lens: [1.0, 1.0],
+            weld_seams: false,
+        }
+    }
+}
+
+impl ISweepSurface {
+    // a path or a cross-section of fewer than 2 points gives an empty output
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let path:Vec<Vector3<f32>> = self.path.points(self.u_resolution).iter().map(|p| Vector3::from(*p)).collect();
+        let section = self.cross_section.points(self.v_resolution);
+        if path.len() < 2 || section.len() < 2 {
+            return ISurfaceOutput::default();
+        }
+        let section3:Vec<Vector3<f32>> = section.iter().map(|p| Vector3::new(p[0], p[1], 0.0)).collect();
+        let (nu, nv) = (path.len() - 1, section.len() - 1);
+        let (path_closed, section_closed) = (is_closed(&path), is_closed(&section3));
+
+        let frames = rotation_minimizing_frames(&path, path_closed);
+        let (su, sv) = (arc_length_params(&path), arc_length_params(&section3));
+
+        let mut pts: Vec<Vector3<f32>> = vec![];
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=nu {
+            let (_, n, b) = frames[i];
+            for j in 0..=nv {
+                let pos = path[i] + n * section[j][0] + b * section[j][1];
+                pts.push(pos);
+                out.positions.push(pos.into());
+                out.uvs.push([self.uv_lens[0] * su[i], self.uv_lens[1] * sv[j]]);
+            }
+        }
+        out.normals = grid_normals(&pts, nu, nv, path_closed, section_closed);
+        let seams = [(self.weld_seams && path_closed) as u32, (self.weld_seams && section_closed) as u32];
+        (out.indices, out.indices2) = periodic_grid_indices(nu as u32, nv as u32, seams);
+
+        if self.caps && section_closed && !path_closed {
+            let first = out.positions[0..=nv].to_vec();
+            let last = out.positions[nu * (nv + 1)..].to_vec();
+            add_cap(&mut out, &first, -frames[0].0);
+            add_cap(&mut out, &last, frames[nu].0);
+        }
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.compute_tangents();
+
+        out.normalize_positions(self.scale);
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        out
+    }
+}
+// endregion: surface of revolution and sweep surface
+// region: spherical and polar surfaces
+pub struct ISphericalSurface {
+    pub f: Box<dyn Fn(f32, f32) -> f32>, // signed radius r(theta, phi); the surface is drawn at |r|
+    pub theta_resolution: u16, // samples from the +y pole (theta = 0) to the -y pole (theta = pi)
+    pub phi_resolution: u16, // samples around the y-axis
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction, 3: sign of r(theta, phi)
+    pub uv_lens: [f32; 2],
+    pub weld_seams: bool, // share the vertices of closed seams, off by default as in IParametricSurface
+}
+
+impl Default for ISphericalSurface {
+    fn default() -> Self {
+        Self {
+            f: Box::new(|theta, phi| mf::spherical_harmonic(3, 2, theta, phi)),
+            theta_resolution: 64,
+            phi_resolution: 64,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 3,
+            uv_lens: [1.0, 1.0],
+            weld_seams: false,
+        }
+    }
+}
+
+impl ISphericalSurface {
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let (nu, nv) = (self.theta_resolution as usize, self.phi_resolution as usize);
+        let mut pts: Vec<Vector3<f32>> = vec![];
+        let mut signs: Vec<f32> = vec![];
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=nu {
+            let theta = PI * i as f32 / nu as f32;
+            for j in 0..=nv {
+                let phi = 2.0 * PI * j as f32 / nv as f32;
+                let r = (self.f)(theta, phi);
+                pts.push(Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()) * r.abs());
+                signs.push(if r < 0.0 { -1.0 } else { 1.0 });
+                out.uvs.push([self.uv_lens[0] * i as f32 / nu as f32, self.uv_lens[1] * j as f32 / nv as f32]);
+            }
+        }
+
+        // the rows at the poles collapse into a point, so they take the mean normal of the next row
+        out.normals = grid_normals(&pts, nu, nv, false, true);
+        for (i, i1) in [(0, 1), (nu, nu - 1)] {
+            let n = (0..nv).fold(Vector3::new(0.0f32, 0.0, 0.0), |n, j| n + Vector3::from(out.normals[j + i1 * (nv + 1)]));
+            let n:[f32; 3] = if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 1.0, 0.0] };
+            for j in 0..=nv {
+                if out.normals[j + i * (nv + 1)] == [0.0, 0.0, 0.0] {
+                    out.normals[j + i * (nv + 1)] = n;
                 }
             }
         }
+        (out.indices, out.indices2) = periodic_grid_indices(nu as u32, nv as u32, [0, self.weld_seams as u32]);
 
-        ISurfaceOutput { positions, normals, colors, colors2, uvs, indices, indices2 }
+        // keep the origin at the center so that the lobes stay in place
+        let rmax = pts.iter().filter(|p| p.magnitude().is_finite()).fold(0.0f32, |m, p| m.max(p.magnitude()));
+        let k = if rmax > 0.0 { self.scale / rmax } else { self.scale };
+        out.positions = pts.iter().map(|p| (p * k).into()).collect();
+
+        if self.colormap_direction == 3 {
+            let cdata = colormap::colormap_data(&self.colormap_name);
+            let cdata2 = colormap::colormap_data(&self.wireframe_color);
+            out.colors = signs.iter().map(|&s| colormap::color_lerp(cdata, -1.0, 1.0, s)).collect();
+            out.colors2 = signs.iter().map(|&s| colormap::color_lerp(cdata2, -1.0, 1.0, s)).collect();
+        }
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.compute_tangents();
+        if self.colormap_direction != 3 {
+            out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        }
+        out
     }
+}
 
-    fn normalize_data(&mut self, point:[f32; 3], ymin:f32, ymax:f32) -> [f32; 3] {
-        let mut pt = point.clone();
-        pt[0] = (-1.0 + 2.0 * (pt[0] - self.xmin) / (self.xmax - self.xmin)) * self.scale;
-        pt[1] = (-1.0 + 2.0 * (pt[1] - ymin) / (ymax - ymin)) * self.scale * self.aspect_ratio;
-        pt[2] = (-1.0 + 2.0 * (pt[2] - self.zmin) / (self.zmax - self.zmin)) * self.scale;
-        pt
+pub struct IPolarSurface {
+    pub f: Box<dyn Fn(f32, f32, f32) -> f32>, // height y = f(r, theta, t)
+    pub rmin: f32,
+    pub rmax: f32,
+    pub r_resolution: u16,
+    pub theta_resolution: u16,
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub t: f32,  // animation time parameter
+    pub uv_lens: [f32; 2],
+    pub weld_seams: bool, // share the vertices of closed seams, off by default as in IParametricSurface
+}
+
+impl Default for IPolarSurface {
+    fn default() -> Self {
+        Self {
+            f: Box::new(mf::membrane),
+            rmin: 0.0,
+            rmax: 1.0,
+            r_resolution: 30,
+            theta_resolution: 64,
+            scale: 1.0,
+            aspect_ratio: 0.5,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            t: 0.0,
+            uv_lens: [1.0, 1.0],
+            weld_seams: false,
+        }
     }
+}
 
-    fn yrange(&mut self, f:&dyn Fn(f32, f32, f32) -> [f32; 3]) -> (f32, f32) {
-        let dx = (self.xmax- self.xmin) / self.x_resolution as f32;
-        let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
-        let mut ymin = f32::MAX;
-        let mut ymax = f32::MIN;
-    
-        for i in 0..=self.x_resolution {
-            let x = self.xmin + dx * i as f32;
-            for j in 0..=self.z_resolution {
-                let z = self.zmin + dz * j as f32;
-                let pt = f(x, z, self.t);
-                ymin = if pt[1] < ymin { pt[1] } else { ymin };
-                ymax = if pt[1] > ymax { pt[1] } else { ymax };
+impl IPolarSurface {
+    // the disk (or annulus) of radius rmax is mapped to radius scale, and y to [-1, 1] * scale * aspect_ratio
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let (nu, nv) = (self.r_resolution as usize, self.theta_resolution as usize);
+        let dr = (self.rmax - self.rmin) / nu as f32;
+        let dtheta = 2.0 * PI / nv as f32;
+
+        let mut heights: Vec<f32> = vec![];
+        for i in 0..=nu {
+            for j in 0..=nv {
+                heights.push((self.f)(self.rmin + dr * i as f32, dtheta * j as f32, self.t));
+            }
+        }
+        let ymin = heights.iter().cloned().filter(|h| h.is_finite()).fold(f32::MAX, f32::min);
+        let ymax = heights.iter().cloned().filter(|h| h.is_finite()).fold(f32::MIN, f32::max).max(ymin + f32::EPSILON);
+        let ky = 2.0 * self.scale * self.aspect_ratio / (ymax - ymin);
+        let kx = self.scale / self.rmax;
+
+        // normals from the cartesian slope of the height, which stays well defined at r = 0
+        let g = |x:f32, z:f32| (self.f)((x * x + z * z).sqrt(), z.atan2(x), self.t);
+        let eps = 1e-3 * self.rmax;
+
+        let mut out = ISurfaceOutput::default();
+        for i in 0..=nu {
+            let r = self.rmin + dr * i as f32;
+            for j in 0..=nv {
+                let theta = dtheta * j as f32;
+                let (x, z) = (r * theta.cos(), r * theta.sin());
+                out.positions.push([kx * x, -self.scale * self.aspect_ratio + ky * (heights[j + i * (nv + 1)] - ymin), kx * z]);
+
+                let gx = (g(x + eps, z) - g(x - eps, z)) / (2.0 * eps);
+                let gz = (g(x, z + eps) - g(x, z - eps)) / (2.0 * eps);
+                let normal = Vector3::new(-ky * gx / kx, 1.0, -ky * gz / kx).normalize();
+                out.normals.push(normal.into());
+
+                out.uvs.push([self.uv_lens[0] * i as f32 / nu as f32, self.uv_lens[1] * j as f32 / nv as f32]);
+            }
+        }
+        (out.indices, out.indices2) = periodic_grid_indices(nu as u32, nv as u32, [0, self.weld_seams as u32]);
+        out.reject_non_finite();
+        out.remove_unused_vertices();
+        out.compute_tangents();
+        out.set_colormap(&self.colormap_name, &self.wireframe_color, self.colormap_direction);
+        out
+    }
+}
+// endregion: spherical and polar surfaces
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid(name:&str, out:&ISurfaceOutput) {
+        assert!(!out.positions.is_empty(), "{}: no vertices", name);
+        for p in out.positions.iter().chain(out.normals.iter()) {
+            assert!(p.iter().all(|c| c.is_finite()), "{}: non-finite vertex {:?}", name, p);
+        }
+        assert_eq!(out.indices.len() % 3, 0, "{}", name);
+        assert_eq!(out.indices2.len() % 2, 0, "{}", name);
+        let n = out.positions.len() as u32;
+        assert!(out.indices.iter().chain(out.indices2.iter()).all(|&i| i < n), "{}: index out of range", name);
+    }
+
+    // superShape3D of super_shape_comp.wgsl as first written, with the two terms raised to their powers
+    // on their own, in f64 where they don't overflow
+    fn wgsl_super_shape(u:f64, v:f64, t:f64, n1:[f32; 4], n2:[f32; 4], a1:[f32; 2], a2:[f32; 2]) -> [f64; 3] {
+        let radius = |angle:f64, a:[f32; 2], n:[f32; 4]| {
+            let [m, n1, n2, n3] = n.map(|x| x as f64);
+            let raux = (1.0 / a[0] as f64 * (m * angle / 4.0).cos()).abs().powf(n2) +
+                (1.0 / a[1] as f64 * (m * angle / 4.0).sin()).abs().powf(n3);
+            raux.abs().powf(-1.0 / n1)
+        };
+        let (r1, r2) = (radius(u, a1, n1), radius(v, a2, n2));
+        let v1 = v * 0.334 * (2.0 + t.sin());
+        [r1 * u.cos() * r2 * v1.cos(), r2 * v1.sin(), r1 * u.sin() * r2 * v1.cos()]
+    }
+
+    #[test]
+    fn super_shape_presets() {
+        for key in 0..=8 {
+            let mut shape = ISuperShape { u_resolution: 63, v_resolution: 63, aspect_ratio: 1.5, t: 0.7, ..Default::default() };
+            shape.set_preset(key);
+            let out = shape.new();
+            assert_valid(&format!("preset {}", key), &out);
+            assert_eq!(out.rejected, 0, "preset {}", key);
+            assert_eq!(out.indices.len(), 6 * 63 * 63);
+
+            // vertices against the compute shader, away from the samples where a term is 0
+            for (i, j) in [(5, 10), (20, 40), (40, 27), (58, 50)] {
+                let (u, v) = (-PI + 2.0 * PI * i as f32 / 63.0, -0.5 * PI + PI * j as f32 / 63.0);
+                let p = wgsl_super_shape(u as f64, v as f64, 0.7, shape.n1, shape.n2, shape.a1, shape.a2);
+                let p = [p[0], 1.5 * p[1], p[2]].map(|x| shape.scale as f64 * x);
+                let q = out.positions[j + 64 * i];
+                for k in 0..3 {
+                    assert!((q[k] as f64 - p[k]).abs() <= 1e-4 * (1.0 + p[k].abs()), "preset {} ({}, {}): {:?} {:?}", key, i, j, q, p);
+                }
             }
         }
-        (ymin, ymax)
+
+        // terms of preset 5 overflow f32 well away from v = 0, but the radius only has a pole there,
+        // the |sin(7v/4)|^-56.7 term going to infinity, so an even v_resolution, which samples v = 0,
+        // loses exactly that row
+        let mut shape = ISuperShape::default();
+        shape.set_preset(5);
+        let out = shape.new();
+        assert_valid("preset 5", &out);
+        assert_eq!(out.rejected, 65);
+        assert!(out.positions.iter().all(|p| p[1].abs() > 0.0));
+    }
+
+    // every vertex left is referenced, so a non-finite sample can't reach the bounding box or colormap
+    fn assert_compact(name:&str, out:&ISurfaceOutput) {
+        let mut used = vec![false; out.positions.len()];
+        for &i in out.indices.iter().chain(out.indices2.iter()) {
+            used[i as usize] = true;
+        }
+        assert!(used.iter().all(|&u| u), "{}: unused vertices", name);
+        assert!(out.rejected > 0, "{}: nothing rejected", name);
+    }
+
+    #[test]
+    fn generators_reject_non_finite_samples() {
+        let mut spherical = ISphericalSurface {
+            f: Box::new(|theta, _| if theta > 1.0 && theta < 1.3 { f32::NAN } else { 1.0 }),
+            ..Default::default()
+        };
+        let mut polar = IPolarSurface {
+            f: Box::new(|r, _, _| if r > 0.4 && r < 0.5 { f32::NAN } else { r }),
+            ..Default::default()
+        };
+        let mut revolution = IRevolutionSurface {
+            profile: IProfileCurve::Func(Box::new(|s| if s > 0.4 && s < 0.5 { [f32::NAN, s] } else { [1.0, s] })),
+            ..Default::default()
+        };
+        let mut tube = super::super::curve_data::ITubeCurve {
+            radius: Box::new(|t| if t > 1.0 && t < 1.2 { f32::INFINITY } else { 0.3 }),
+            ..Default::default()
+        };
+        let mut scatter = super::super::scatter_data::IScatterSurface {
+            points: vec![[0.0, 0.0, 0.0], [1.0, 0.5, 0.0], [0.0, 0.2, 1.0], [1.0, 1.0, 1.0], [f32::NAN, 0.0, 0.5]],
+            ..Default::default()
+        };
+        for (name, out) in [("spherical", spherical.new()), ("polar", polar.new()), ("revolution", revolution.new()),
+            ("tube", tube.new()), ("scatter", scatter.new())] {
+            assert_valid(name, &out);
+            assert_compact(name, &out);
+        }
+    }
+
+    #[test]
+    fn thin_tubes_keep_their_caps() {
+        for tube_resolution in 0..=3 {
+            let mut tube = super::super::curve_data::ITubeCurve {
+                f: Box::new(|t| [t, 0.0, 0.0]),
+                tmin: 0.0,
+                tmax: 1.0,
+                t_resolution: 4,
+                tube_resolution,
+                ..Default::default()
+            };
+            let out = tube.new();
+            let name = format!("tube_resolution {}", tube_resolution);
+            assert_valid(&name, &out);
+            assert_eq!(out.colors.len(), out.positions.len(), "{}", name);
+            // a 3-sided tube of 4 segments and its two caps
+            assert_eq!(out.indices.len(), 6 * 4 * 3 + 2 * 3 * 3, "{}", name);
+        }
+    }
+
+    #[test]
+    fn degenerate_curves_give_empty_outputs() {
+        let revolutions = [
+            IRevolutionSurface { profile: IProfileCurve::Polyline(vec![]), ..Default::default() },
+            IRevolutionSurface { profile: IProfileCurve::Polyline(vec![[1.0, 0.0]]), ..Default::default() },
+            IRevolutionSurface { u_resolution: 0, ..Default::default() },
+            IRevolutionSurface { v_resolution: 0, ..Default::default() },
+        ];
+        for mut surface in revolutions {
+            let out = surface.new();
+            assert!(out.positions.is_empty() && out.indices.is_empty());
+        }
+        let sweeps = [
+            ISweepSurface { path: IPathCurve::Polyline(vec![]), ..Default::default() },
+            ISweepSurface { cross_section: IProfileCurve::Polyline(vec![[0.1, 0.0]]), ..Default::default() },
+            ISweepSurface { u_resolution: 0, ..Default::default() },
+            ISweepSurface { v_resolution: 0, ..Default::default() },
+        ];
+        for mut surface in sweeps {
+            let out = surface.new();
+            assert!(out.positions.is_empty() && out.indices.is_empty());
+        }
+        // two points are enough
+        let mut band = IRevolutionSurface { profile: IProfileCurve::Polyline(vec![[1.0, 0.0], [1.0, 1.0]]), ..Default::default() };
+        assert_valid("band", &band.new());
+    }
+
+    // edges used by a single triangle, counted on the indices as they are
+    fn boundary_edges(out:&ISurfaceOutput) -> usize {
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in out.indices.chunks(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        edges.values().filter(|&&n| n == 1).count()
+    }
+
+    #[test]
+    fn welded_klein_bottles_are_closed() {
+        for key in [0, 11] {
+            let mut surface = IParametricSurface { surface_type: key, u_resolution: 16, v_resolution: 16,
+                weld_seams: true, ..Default::default() };
+            let out = surface.new();
+            assert_valid("klein bottle", &out);
+            assert_eq!(boundary_edges(&out), 0, "surface type {}", key);
+        }
+    }
+
+    #[test]
+    fn every_generator_fills_tangents() {
+        use super::super::{curve_data, minimal_data, nurbs_data, scatter_data, subdivision};
+        let mut simple = ISimpleSurface::default();
+        let complex = simple.complex_surface_data(&|z, _| z * z - 1.0, 0, 10.0, false);
+        let outputs = [
+            ("parametric", IParametricSurface::default().new()),
+            ("simple", ISimpleSurface::default().new()),
+            ("complex", complex),
+            ("super shape", ISuperShape::default().new()),
+            ("revolution", IRevolutionSurface::default().new()),
+            ("sweep", ISweepSurface::default().new()),
+            ("spherical", ISphericalSurface::default().new()),
+            ("polar", IPolarSurface::default().new()),
+            ("tube", curve_data::ITubeCurve::default().new()),
+            ("nurbs", nurbs_data::INurbsSurface::default().new().unwrap()),
+            ("minimal", minimal_data::IMinimalSurface::default().new()),
+            ("scatter", scatter_data::IScatterSurface {
+                points: vec![[0.0, 0.0, 0.0], [1.0, 0.5, 0.0], [0.0, 0.2, 1.0], [1.0, 1.0, 1.0], [0.4, 0.3, 0.6]],
+                ..Default::default()
+            }.new()),
+            ("subdivision", subdivision::catmull_clark(&subdivision::cube_surface_data(2.0), 1)),
+        ];
+        for (name, out) in outputs.iter() {
+            assert_eq!(out.tangents.len(), out.positions.len(), "{}", name);
+            for (t, n) in out.tangents.iter().zip(out.normals.iter()) {
+                let (tv, nv) = (Vector3::new(t[0], t[1], t[2]), Vector3::from(*n));
+                assert!((tv.magnitude() - 1.0).abs() < 1e-3, "{}: tangent {:?}", name, t);
+                assert!(tv.dot(nv).abs() < 1e-3, "{}: tangent {:?} not orthogonal to {:?}", name, t, n);
+                assert!(t[3] == 1.0 || t[3] == -1.0, "{}", name);
+            }
+        }
+    }
+
+    fn assert_same(name:&str, a:&ISurfaceOutput, b:&ISurfaceOutput) {
+        assert_eq!(a.positions, b.positions, "{}", name);
+        assert_eq!(a.normals, b.normals, "{}", name);
+        assert_eq!(a.tangents, b.tangents, "{}", name);
+        assert_eq!(a.colors, b.colors, "{}", name);
+        assert_eq!(a.colors2, b.colors2, "{}", name);
+        assert_eq!(a.uvs, b.uvs, "{}", name);
+        assert_eq!(a.indices, b.indices, "{}", name);
+        assert_eq!(a.indices2, b.indices2, "{}", name);
+        assert_eq!(a.rejected, b.rejected, "{}", name);
+    }
+
+    #[test]
+    fn update_time_matches_new() {
+        for (key, direction, normalization) in [(0, 1, 0), (1, 0, 0), (2, 2, 1), (7, 1, 1)] {
+            let preset = |t| ISimpleSurface { surface_type: key, colormap_direction: direction, normalization,
+                y_range: [-2.0, 3.0], t, ..Default::default() };
+            let mut surface = preset(0.0);
+            let mut out = surface.new();
+            for t in [0.3, 1.7, 4.0] {
+                surface.update_time(t, &mut out);
+                let mut fresh = preset(t);
+                assert_same(&format!("key {} at t = {}", key, t), &out, &fresh.new());
+            }
+        }
+    }
+
+    #[test]
+    fn update_time_opens_and_closes_holes() {
+        // a hole that is open for t < 1 only
+        let f = |x:f32, z:f32, t:f32| {
+            let y = if t < 1.0 && x.abs() < 0.3 && z.abs() < 0.3 { f32::NAN } else { x * z + t };
+            [x, y, z]
+        };
+        let mut surface = ISimpleSurface { colormap_direction: 0, ..Default::default() };
+        let mut out = ISurfaceOutput::default();
+        surface.simple_surface_data(&f, &mut out);
+        assert!(out.rejected > 0);
+        for t in [0.5, 2.0, 0.5, 0.7, 3.0] {
+            surface.t = t;
+            let dirty = surface.update_surface_data(&f, &mut out);
+            assert!(dirty.colors && dirty.indices, "t = {}", t);
+            let mut fresh = ISurfaceOutput::default();
+            surface.simple_surface_data(&f, &mut fresh);
+            assert_same(&format!("t = {}", t), &out, &fresh);
+        }
     }
 }
-// endregion: simple surface
\ No newline at end of file
@@ -1,4 +1,5 @@
 use cgmath::*;
+use super::surface_data::ISurfaceOutput;
 
 pub fn torus_position(r_torus:f32, r_tube:f32, u:Deg<f32>, v: Deg<f32>) -> [f32; 3] {
     let x = (r_torus + r_tube * v.cos()) * u.cos();
@@ -8,7 +9,7 @@ pub fn torus_position(r_torus:f32, r_tube:f32, u:Deg<f32>, v: Deg<f32>) -> [f32;
 }
 
 pub fn create_torus_data(r_torus:f32, r_tube:f32, n_torus:u16, n_tube:u16) 
--> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>, Vec<u16>) {
+-> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>, Vec<u32>) {
     let mut positions: Vec<[f32; 3]> = vec![];
     let mut normals: Vec<[f32; 3]> = vec![];
     let eps = 0.01 * 360.0/n_tube as f32;
@@ -30,19 +31,19 @@ pub fn create_torus_data(r_torus:f32, r_tube:f32, n_torus:u16, n_tube:u16)
         }
     }
 
-    let mut indices: Vec<u16> = vec![];
-    let mut indices2: Vec<u16> = vec![];
-    let vertices_per_row = n_tube + 1;
+    let mut indices: Vec<u32> = vec![];
+    let mut indices2: Vec<u32> = vec![];
+    let vertices_per_row = n_tube as u32 + 1;
 
-    for i in 0..n_torus {
-        for j in 0..n_tube {
+    for i in 0..n_torus as u32 {
+        for j in 0..n_tube as u32 {
             let idx0 = j + i * vertices_per_row;
             let idx1 = j + 1 + i * vertices_per_row;
             let idx2 = j + 1 + (i + 1) * vertices_per_row;
             let idx3 = j + (i + 1) * vertices_per_row; 
-            let values:Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
+            let values:Vec<u32> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
             indices.extend(values);
-            let values2:Vec<u16> = vec![idx0, idx1, idx0, idx3];
+            let values2:Vec<u32> = vec![idx0, idx1, idx0, idx3];
             indices2.extend(values2);
         }
     }
@@ -57,7 +58,7 @@ fn cylinder_position(r:f32, theta:Deg<f32>, y:f32) -> [f32; 3] {
     [x, y, z]
 }
 
-pub fn create_cylinder_data(mut rin:f32, rout:f32, h:f32, n:u16) -> (Vec<[f32; 3]>, Vec<u16>, Vec<u16>) {
+pub fn create_cylinder_data(mut rin:f32, rout:f32, h:f32, n:u16) -> (Vec<[f32; 3]>, Vec<u32>, Vec<u32>) {
     if rin >= 0.999 * rout { 
         rin = 0.999 * rout; 
     }
@@ -73,10 +74,10 @@ pub fn create_cylinder_data(mut rin:f32, rout:f32, h:f32, n:u16) -> (Vec<[f32; 3
         positions.extend(values);
     }
 
-    let mut indices: Vec<u16> = vec![];
-    let mut indices2: Vec<u16> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    let mut indices2: Vec<u32> = vec![];
 
-    for i in 0..n {
+    for i in 0..n as u32 {
         let idx0 = i*4;
         let idx1 = i*4 + 1;
         let idx2 = i*4 + 2;
@@ -87,7 +88,7 @@ pub fn create_cylinder_data(mut rin:f32, rout:f32, h:f32, n:u16) -> (Vec<[f32; 3
         let idx7 = i*4 + 7;
 
         // triangle indices
-        let values: Vec<u16> = vec![
+        let values: Vec<u32> = vec![
             idx0, idx4, idx7, idx7, idx3, idx0, // top
             idx1, idx2, idx6, idx6, idx5, idx1, // bottom
             idx0, idx1, idx5, idx5, idx4, idx0, // outer
@@ -96,7 +97,7 @@ pub fn create_cylinder_data(mut rin:f32, rout:f32, h:f32, n:u16) -> (Vec<[f32; 3
         indices.extend(values);
 
         // wireframe indices
-        let values2: Vec<u16> = vec![
+        let values2: Vec<u32> = vec![
             idx0, idx3, idx3, idx7, idx4, idx0, // top
             idx1, idx2, idx2, idx6, idx5, idx1, // bottom
             idx0, idx1, idx3, idx2              // side
@@ -115,7 +116,7 @@ fn sphere_position(r:f32, theta:Deg<f32>, phi:Deg<f32>) -> [f32; 3] {
 }
 
 pub fn create_sphere_data(r:f32, u:u16, v:u16) -> 
-(Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<[f32; 2]>, Vec<u16>, Vec<u16>) {
+(Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<[f32; 2]>, Vec<u32>, Vec<u32>) {
     let mut positions: Vec<[f32; 3]> = vec![];
     let mut normals: Vec<[f32; 3]> = vec![];
     let mut uvs: Vec<[f32; 2]> = vec![];
@@ -131,20 +132,20 @@ pub fn create_sphere_data(r:f32, u:u16, v:u16) ->
         }
     }
 
-    let mut indices: Vec<u16> = vec![];
-    let mut indices2: Vec<u16> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    let mut indices2: Vec<u32> = vec![];
     
-    for i in 0..u {
-        for j in 0..v {
-            let idx0 = j + i * (v as u16 + 1);
-            let idx1 = j + 1 + i * (v as u16 + 1);
-            let idx2 = j + 1 + (i + 1) * (v as u16 + 1);
-            let idx3 = j + (i + 1) * (v as u16 + 1);
-
-            let values: Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
+    for i in 0..u as u32 {
+        for j in 0..v as u32 {
+            let idx0 = j + i * (v as u32 + 1);
+            let idx1 = j + 1 + i * (v as u32 + 1);
+            let idx2 = j + 1 + (i + 1) * (v as u32 + 1);
+            let idx3 = j + (i + 1) * (v as u32 + 1);
+
+            let values: Vec<u32> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
             indices.extend(values); 
            
-            let values2: Vec<u16> = vec![idx0, idx1, idx0, idx3];
+            let values2: Vec<u32> = vec![idx0, idx1, idx0, idx3];
             indices2.extend(values2); 
         }
     }
@@ -153,7 +154,7 @@ pub fn create_sphere_data(r:f32, u:u16, v:u16) ->
 }
 
 pub fn create_cube_data(side:f32) -> (Vec<[f32; 3]>, Vec<[f32; 3]>,Vec<[f32; 3]>,
-    Vec<[f32; 2]>,Vec<u16>,Vec<u16>) {
+    Vec<[f32; 2]>,Vec<u32>,Vec<u32>) {
         let s2 = side / 2.0;
         let positions = [
             [s2,  s2,  s2],     // index 0
@@ -223,4 +224,122 @@ pub fn create_cube_data(side:f32) -> (Vec<[f32; 3]>, Vec<[f32; 3]>,Vec<[f32; 3]>
     (positions.to_vec(), colors.to_vec(), normals.to_vec(), uvs.to_vec(), 
      indices.to_vec(), indices2.to_vec())
 }
-    
\ No newline at end of file
+    
+// index buffer contents and format for wgpu: 16-bit indices when every index fits (0xFFFF is kept
+// free for primitive restart), 32-bit otherwise
+pub fn index_buffer_data(indices:&[u32]) -> (Vec<u8>, wgpu::IndexFormat) {
+    if indices.iter().all(|&i| i < u16::MAX as u32) {
+        let data:Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+        (bytemuck::cast_slice(&data).to_vec(), wgpu::IndexFormat::Uint16)
+    } else {
+        (bytemuck::cast_slice(indices).to_vec(), wgpu::IndexFormat::Uint32)
+    }
+}
+
+// triangle and wireframe indices for the resolution x resolution vertex grids the compute shaders
+// write, vertex i + j * resolution, in the order and winding the index compute shader used to make them
+pub fn compute_grid_indices(resolution:u32) -> (Vec<u32>, Vec<u32>) {
+    let n = resolution.saturating_sub(1);
+    let (mut indices, mut indices2) = (vec![], vec![]);
+    for j in 0..n {
+        for i in 0..n {
+            let (i0, i1, i2, i3) = (i + j * resolution, i + 1 + j * resolution, i + (j + 1) * resolution, i + 1 + (j + 1) * resolution);
+            indices.extend([i0, i2, i1, i1, i2, i3]);
+            indices2.extend([i0, i1, i1, i3, i3, i2, i2, i0]);
+        }
+    }
+    (indices, indices2)
+}
+
+// one vertex as the compute shaders write it into their VertexData arrays, so that surfaces made on
+// the CPU can be drawn by the same render pipelines as the ones made on the GPU
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct IVertexData {
+    pub position: [f32; 4],
+    pub normal: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl IVertexData {
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4]; // pos, norm, col
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<IVertexData>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// interleaved vertices of a surface: with the colormap colors for the triangles, and with the
+// wireframe colors for the lines. the w-components are 1 like in the compute shaders.
+pub fn interleaved_vertex_data(mesh:&ISurfaceOutput) -> (Vec<IVertexData>, Vec<IVertexData>) {
+    let vertex = |i:usize, colors:&[[f32; 3]]| {
+        let (p, n) = (mesh.positions[i], mesh.normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]));
+        let c = colors.get(i).copied().unwrap_or([1.0, 1.0, 1.0]);
+        IVertexData { position: [p[0], p[1], p[2], 1.0], normal: [n[0], n[1], n[2], 1.0], color: [c[0], c[1], c[2], 1.0] }
+    };
+    let data = (0..mesh.positions.len()).map(|i| vertex(i, &mesh.colors)).collect();
+    let data2 = (0..mesh.positions.len()).map(|i| vertex(i, &mesh.colors2)).collect();
+    (data, data2)
+}
+
+// vertex buffer contents for the surface and wireframe pipelines, see interleaved_vertex_data
+pub fn vertex_buffer_data(mesh:&ISurfaceOutput) -> (Vec<u8>, Vec<u8>) {
+    let (data, data2) = interleaved_vertex_data(mesh);
+    (bytemuck::cast_slice(&data).to_vec(), bytemuck::cast_slice(&data2).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::surface_data::{grid_indices, periodic_grid_indices, IParametricSurface};
+
+    #[test]
+    fn large_grids_use_32_bit_indices() {
+        let n = 1024;
+        let count = (n + 1) * (n + 1);
+        let grids = [grid_indices(n, n), periodic_grid_indices(n, n, [1, 1]), periodic_grid_indices(n, n, [2, 1])];
+        for (indices, indices2) in grids.iter() {
+            assert!(indices.iter().chain(indices2.iter()).all(|&i| i < count));
+            let (data, format) = index_buffer_data(indices);
+            assert_eq!(format, wgpu::IndexFormat::Uint32);
+            assert_eq!(data.len(), 4 * indices.len());
+        }
+        let (indices, _) = grid_indices(8, 8);
+        let (data, format) = index_buffer_data(&indices);
+        assert_eq!(format, wgpu::IndexFormat::Uint16);
+        assert_eq!(data.len(), 2 * indices.len());
+    }
+
+    #[test]
+    fn surfaces_and_examples_pick_the_index_format() {
+        // the generator path
+        let surface = |resolution:u16| IParametricSurface {
+            u_resolution: resolution, v_resolution: resolution, ..Default::default()
+        }.new();
+        let large = surface(1024);
+        assert!(large.positions.len() > u16::MAX as usize);
+        assert_eq!(index_buffer_data(&large.indices).1, wgpu::IndexFormat::Uint32);
+        assert_eq!(index_buffer_data(&large.indices2).1, wgpu::IndexFormat::Uint32);
+        let small = surface(64);
+        assert_eq!(index_buffer_data(&small.indices).1, wgpu::IndexFormat::Uint16);
+        assert_eq!(index_buffer_data(&small.indices2).1, wgpu::IndexFormat::Uint16);
+
+        // the examples' path, with the layout of the old index compute shader
+        let (indices, indices2) = compute_grid_indices(3);
+        assert_eq!(&indices[..6], &[0, 3, 1, 1, 3, 4]);
+        assert_eq!(&indices2[..8], &[0, 1, 1, 4, 4, 3, 3, 0]);
+        assert_eq!((indices.len(), indices2.len()), (6 * 4, 8 * 4));
+        let (indices, indices2) = compute_grid_indices(1024);
+        assert_eq!(*indices.iter().max().unwrap(), 1024 * 1024 - 1);
+        let (data, format) = index_buffer_data(&indices);
+        assert_eq!((format, data.len()), (wgpu::IndexFormat::Uint32, 4 * indices.len()));
+        assert_eq!(index_buffer_data(&indices2).1, wgpu::IndexFormat::Uint32);
+        let (data, format) = index_buffer_data(&compute_grid_indices(64).0);
+        assert_eq!((format, data.len()), (wgpu::IndexFormat::Uint16, 2 * 6 * 63 * 63));
+    }
+}